@@ -0,0 +1,493 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::monomial::support;
+use super::anf::VariableNames;
+use super::word::Word;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// A combinational and-inverter graph in AIGER numbering: literal `2 * v`
+/// is variable `v` and `2 * v + 1` its negation, `0` and `1` the constants.
+/// Inputs are variables `1..=I`, and gates follow in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aiger {
+    inputs: Vec<Variable>,
+    ands: Vec<(usize, usize)>,
+    outputs: Vec<(String, usize)>,
+}
+
+struct Builder<'a> {
+    f: &'a Forest,
+    aiger: Aiger,
+    input_lits: HashMap<Variable, usize>,
+    strash: HashMap<(usize, usize), usize>,
+    memo: HashMap<NodeIdx, usize>,
+}
+
+impl<'a> Builder<'a> {
+    fn and(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = if a > b { (a, b) } else { (b, a) };
+        if b == 0 || a == b ^ 1 { return 0 }
+        if b == 1 || a == b { return a }
+        if let Some(&lit) = self.strash.get(&(a, b)) {
+            return lit;
+        }
+
+        let lit = 2 * (self.aiger.inputs.len() + self.aiger.ands.len() + 1);
+        self.aiger.ands.push((a, b));
+        self.strash.insert((a, b), lit);
+        lit
+    }
+
+    fn xor(&mut self, a: usize, b: usize) -> usize {
+        let a_b = self.and(a, b ^ 1);
+        let b_a = self.and(a ^ 1, b);
+        self.and(a_b ^ 1, b_a ^ 1) ^ 1
+    }
+
+    /// `x * hi + lo` for every node.
+    fn node(&mut self, idx: NodeIdx) -> usize {
+        if idx < 2 { return idx }
+        if let Some(&lit) = self.memo.get(&idx) {
+            return lit;
+        }
+
+        let Node(var, hi, lo) = self.f.to_node(idx);
+        let x = self.input_lits[&var];
+        let hi = self.node(hi);
+        let lo = self.node(lo);
+        let product = self.and(x, hi);
+        let lit = self.xor(product, lo);
+        self.memo.insert(idx, lit);
+        lit
+    }
+}
+
+fn write_delta<W: Write>(writer: &mut W, mut delta: usize) -> io::Result<()> {
+    while delta >= 0x80 {
+        try!(writer.write_all(&[(delta & 0x7f) as u8 | 0x80]));
+        delta >>= 7;
+    }
+    writer.write_all(&[delta as u8])
+}
+
+impl Aiger {
+    /// Synthesizes the polynomials as outputs, each XOR of the ANF becoming
+    /// three AND gates. The inputs are the variables they depend on, in
+    /// ascending order.
+    pub fn from_polynomials(f: &Forest, outputs: &[(&str, NodeIdx)]) -> Aiger {
+        let mut inputs: Vec<Variable> = Vec::new();
+        for &(_, p) in outputs {
+            inputs.extend(support(f, p));
+        }
+        inputs.sort();
+        inputs.dedup();
+
+        let mut builder = Builder {
+            f: f,
+            input_lits: inputs.iter().enumerate().map(|(i, &var)| (var, 2 * (i + 1))).collect(),
+            aiger: Aiger {
+                inputs: inputs,
+                ands: Vec::new(),
+                outputs: Vec::new(),
+            },
+            strash: HashMap::new(),
+            memo: HashMap::new(),
+        };
+        for &(name, p) in outputs {
+            let lit = builder.node(p);
+            builder.aiger.outputs.push((name.to_string(), lit));
+        }
+        builder.aiger
+    }
+
+    /// The bits of `word` as outputs `name[0]` to `name[31]`.
+    pub fn from_word(f: &Forest, name: &str, word: &Word) -> Aiger {
+        let names: Vec<String> = (0..32).map(|i| format!("{}[{}]", name, i)).collect();
+        let outputs: Vec<(&str, NodeIdx)> = names.iter().enumerate().map(|(i, name)| (&name[..], word.get_bit(i))).collect();
+        Aiger::from_polynomials(f, &outputs)
+    }
+
+    pub fn inputs(&self) -> &[Variable] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[(String, usize)] {
+        &self.outputs
+    }
+
+    pub fn ands(&self) -> &[(usize, usize)] {
+        &self.ands
+    }
+
+    fn write_symbols<W: Write>(&self, writer: &mut W, names: &VariableNames) -> io::Result<()> {
+        for (i, &var) in self.inputs.iter().enumerate() {
+            try!(writeln!(writer, "i{} {}", i, names.name(var)));
+        }
+        for (i, &(ref name, _)) in self.outputs.iter().enumerate() {
+            try!(writeln!(writer, "o{} {}", i, name));
+        }
+        Ok(())
+    }
+
+    fn header(&self) -> String {
+        let i = self.inputs.len();
+        let a = self.ands.len();
+        format!("{} {} 0 {} {}", i + a, i, self.outputs.len(), a)
+    }
+
+    /// Writes the ASCII `.aag` format, with the input variables and output
+    /// names in the symbol table.
+    pub fn write_ascii<W: Write>(&self, writer: &mut W, names: &VariableNames) -> io::Result<()> {
+        try!(writeln!(writer, "aag {}", self.header()));
+        for i in 0..self.inputs.len() {
+            try!(writeln!(writer, "{}", 2 * (i + 1)));
+        }
+        for &(_, lit) in &self.outputs {
+            try!(writeln!(writer, "{}", lit));
+        }
+        let first = self.inputs.len() + 1;
+        for (k, &(a, b)) in self.ands.iter().enumerate() {
+            try!(writeln!(writer, "{} {} {}", 2 * (first + k), a, b));
+        }
+        self.write_symbols(writer, names)
+    }
+
+    /// Writes the binary `.aig` format.
+    pub fn write_binary<W: Write>(&self, writer: &mut W, names: &VariableNames) -> io::Result<()> {
+        try!(writeln!(writer, "aig {}", self.header()));
+        for &(_, lit) in &self.outputs {
+            try!(writeln!(writer, "{}", lit));
+        }
+        let first = self.inputs.len() + 1;
+        for (k, &(a, b)) in self.ands.iter().enumerate() {
+            let lhs = 2 * (first + k);
+            try!(write_delta(writer, lhs - a));
+            try!(write_delta(writer, a - b));
+        }
+        self.write_symbols(writer, names)
+    }
+}
+
+#[derive(Debug)]
+pub enum AigerError {
+    Io(io::Error),
+    /// A line or header that could not be read.
+    Parse(String),
+    /// Latches are not supported.
+    Sequential,
+}
+
+impl fmt::Display for AigerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AigerError::Io(ref err) => write!(f, "{}", err),
+            AigerError::Parse(ref line) => write!(f, "malformed AIGER line {:?}", line),
+            AigerError::Sequential => write!(f, "AIGER circuit has latches"),
+        }
+    }
+}
+
+impl Error for AigerError {
+    fn description(&self) -> &str {
+        match *self {
+            AigerError::Io(ref err) => err.description(),
+            AigerError::Parse(_) => "malformed AIGER file",
+            AigerError::Sequential => "sequential AIGER circuits are not supported",
+        }
+    }
+}
+
+impl From<io::Error> for AigerError {
+    fn from(err: io::Error) -> AigerError {
+        AigerError::Io(err)
+    }
+}
+
+/// The outputs of a circuit read with `read_aiger`, as polynomials in its
+/// inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AigerCircuit {
+    pub inputs: Vec<Variable>,
+    pub outputs: Vec<(String, NodeIdx)>,
+}
+
+impl AigerCircuit {
+    /// Collects the outputs `name[0]` to `name[31]` into a word, missing
+    /// bits being zero.
+    pub fn word<'a>(&self, forest: &'a RefCell<Forest>, name: &str) -> Word<'a> {
+        let bits: HashMap<String, NodeIdx> = self.outputs.iter().cloned().collect();
+        Word::from_fn(forest, |i| bits.get(&format!("{}[{}]", name, i)).cloned().unwrap_or(0))
+    }
+}
+
+struct Input<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Input<'a> {
+    fn line(&mut self) -> Option<&'a str> {
+        if self.position >= self.data.len() { return None }
+        let start = self.position;
+        while self.position < self.data.len() && self.data[self.position] != b'\n' {
+            self.position += 1;
+        }
+        let line = &self.data[start..self.position];
+        self.position += 1;
+        ::std::str::from_utf8(line).ok().map(|line| line.trim())
+    }
+
+    fn delta(&mut self) -> Result<usize, AigerError> {
+        let mut delta = 0;
+        let mut shift = 0;
+        loop {
+            let byte = match self.data.get(self.position) {
+                Some(&byte) => byte,
+                None => return Err(AigerError::Parse("truncated gate".to_string())),
+            };
+            self.position += 1;
+            if shift >= 64 { return Err(AigerError::Parse("oversized gate delta".to_string())) }
+            delta |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 { return Ok(delta) }
+            shift += 7;
+        }
+    }
+}
+
+fn numbers(line: &str, count: usize) -> Result<Vec<usize>, AigerError> {
+    let numbers: Result<Vec<usize>, _> = line.split_whitespace().map(|n| n.parse()).collect();
+    match numbers {
+        Ok(ref numbers) if numbers.len() == count => Ok(numbers.clone()),
+        _ => Err(AigerError::Parse(line.to_string())),
+    }
+}
+
+/// Reads a combinational circuit in either AIGER format and evaluates its
+/// gates with `multiply` and `add`. Input `i` becomes the variable its
+/// symbol names, see `VariableNames`, or else `Variable` `i`.
+pub fn read_aiger<R: Read>(f: &mut Forest,
+                           mut reader: R,
+                           names: &VariableNames) -> Result<AigerCircuit, AigerError> {
+    let mut data = Vec::new();
+    try!(reader.read_to_end(&mut data));
+    let mut input = Input { data: &data, position: 0 };
+
+    let header = input.line().unwrap_or("");
+    let binary = header.starts_with("aig ");
+    if !binary && !header.starts_with("aag ") {
+        return Err(AigerError::Parse(header.to_string()));
+    }
+    let counts = try!(numbers(&header[4..], 5));
+    let (max, input_count, latches, output_count, and_count) = (counts[0], counts[1], counts[2], counts[3], counts[4]);
+    if latches != 0 { return Err(AigerError::Sequential) }
+    if input_count > 256 { return Err(AigerError::Parse(header.to_string())) }
+
+    let mut input_vars = vec![0; input_count];
+    if !binary {
+        for i in 0..input_count {
+            let line = input.line().unwrap_or("");
+            let lit = try!(numbers(line, 1))[0];
+            if lit & 1 == 1 || lit / 2 > max { return Err(AigerError::Parse(line.to_string())) }
+            input_vars[i] = lit / 2;
+        }
+    } else {
+        for i in 0..input_count {
+            input_vars[i] = i + 1;
+        }
+    }
+
+    let mut output_lits = Vec::new();
+    for _ in 0..output_count {
+        let line = input.line().unwrap_or("");
+        output_lits.push(try!(numbers(line, 1))[0]);
+    }
+
+    let mut gates: HashMap<usize, (usize, usize)> = HashMap::new();
+    for k in 0..and_count {
+        if binary {
+            let lhs = 2 * (input_count + k + 1);
+            // Both deltas are checked, hostile files may point below zero.
+            let a = lhs.checked_sub(try!(input.delta()));
+            let b = match a {
+                Some(a) => a.checked_sub(try!(input.delta())),
+                None => None,
+            };
+            match (a, b) {
+                (Some(a), Some(b)) => { gates.insert(lhs / 2, (a, b)); }
+                _ => return Err(AigerError::Parse(format!("gate {} has a delta past zero", lhs))),
+            }
+        } else {
+            let line = input.line().unwrap_or("");
+            let gate = try!(numbers(line, 3));
+            gates.insert(gate[0] / 2, (gate[1], gate[2]));
+        }
+    }
+
+    let mut inputs: Vec<Variable> = (0..input_count).map(|i| i as Variable).collect();
+    let mut output_names: Vec<String> = (0..output_count).map(|i| format!("o{}", i)).collect();
+    while let Some(line) = input.line() {
+        if line == "c" { break }
+        let mut parts = line.splitn(2, ' ');
+        let (kind, name) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let index: Option<usize> = if kind.len() > 1 { kind[1..].parse().ok() } else { None };
+        match (kind.chars().next(), index) {
+            (Some('i'), Some(i)) if i < input_count => {
+                if let Some(var) = names.variable(name) { inputs[i] = var }
+            }
+            (Some('o'), Some(i)) if i < output_count => output_names[i] = name.to_string(),
+            _ => {}
+        }
+    }
+
+    // Evaluated bottom up with an explicit stack, as ASCII files need not
+    // define gates before their use.
+    let mut values: HashMap<usize, NodeIdx> = HashMap::new();
+    values.insert(0, 0);
+    for (i, &var) in input_vars.iter().enumerate() {
+        let x = f.to_node_idx(Node(inputs[i], 1, 0));
+        values.insert(var, x);
+    }
+
+    let mut outputs = Vec::new();
+    for (i, &lit) in output_lits.iter().enumerate() {
+        let mut stack = vec![lit / 2];
+        while let Some(&var) = stack.last() {
+            if values.contains_key(&var) {
+                stack.pop();
+                continue;
+            }
+            let (a, b) = match gates.get(&var) {
+                Some(&gate) => gate,
+                None => return Err(AigerError::Parse(format!("undefined literal {}", 2 * var))),
+            };
+            match (values.get(&(a / 2)).cloned(), values.get(&(b / 2)).cloned()) {
+                (Some(x), Some(y)) => {
+                    let x = if a & 1 == 1 { add(f, x, 1) } else { x };
+                    let y = if b & 1 == 1 { add(f, y, 1) } else { y };
+                    let value = multiply(f, x, y);
+                    values.insert(var, value);
+                    stack.pop();
+                }
+                (x, y) => {
+                    if stack.len() > 2 * gates.len() + 1 {
+                        return Err(AigerError::Parse(format!("cycle through literal {}", 2 * var)));
+                    }
+                    if x.is_none() { stack.push(a / 2) }
+                    if y.is_none() { stack.push(b / 2) }
+                }
+            }
+        }
+        let value = values[&(lit / 2)];
+        let value = if lit & 1 == 1 { add(f, value, 1) } else { value };
+        outputs.push((output_names[i].clone(), value));
+    }
+
+    Ok(AigerCircuit {
+        inputs: inputs,
+        outputs: outputs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::anf::{VariableNames, parse_anf};
+    use super::super::word::Word;
+
+    use std::cell::RefCell;
+
+    #[test]
+    fn aiger_round_trip() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        let p = parse_anf(f, "x0*x1 + x2*x5 + x1 + 1", &names).unwrap();
+        let q = parse_anf(f, "x1*x2*x5 + x2", &names).unwrap();
+        let outputs = [("p", p), ("q", q), ("one", 1), ("x5", parse_anf(f, "x5", &names).unwrap())];
+
+        let aiger = Aiger::from_polynomials(f, &outputs);
+        assert_eq!(aiger.inputs(), &[0, 1, 2, 5]);
+        for &(a, b) in aiger.ands() {
+            assert!(a >= b);
+        }
+
+        let mut ascii = Vec::new();
+        aiger.write_ascii(&mut ascii, &names).unwrap();
+        assert!(String::from_utf8(ascii.clone()).unwrap().contains("\ni3 x5\no0 p\n"));
+        let mut binary = Vec::new();
+        aiger.write_binary(&mut binary, &names).unwrap();
+        assert!(binary.len() < ascii.len());
+
+        for data in &[ascii, binary] {
+            let circuit = read_aiger(f, &data[..], &names).unwrap();
+            assert_eq!(circuit.inputs, vec![0, 1, 2, 5]);
+            let expected: Vec<(String, NodeIdx)> = outputs.iter().map(|&(name, p)| (name.to_string(), p)).collect();
+            assert_eq!(circuit.outputs, expected);
+        }
+    }
+
+    #[test]
+    fn aiger_half_adder() {
+        // The half adder of the AIGER format description, gates out of
+        // order.
+        let text = "aag 7 2 0 2 3\n2\n4\n6\n12\n6 13 15\n12 2 4\n14 3 5\ni0 x\ni1 y\no0 s\no1 c\n";
+        let f = RefCell::new(Forest::new());
+        let circuit = read_aiger(&mut f.borrow_mut(), text.as_bytes(), &VariableNames::new()).unwrap();
+        {
+            let f = &mut f.borrow_mut();
+            let x0 = f.to_node_idx(Node(0, 1, 0));
+            let x1 = f.to_node_idx(Node(1, 1, 0));
+            let sum = add(f, x0, x1);
+            let carry = multiply(f, x0, x1);
+            assert_eq!(circuit.outputs, vec![("s".to_string(), sum), ("c".to_string(), carry)]);
+        }
+
+        let word = Word::from_fn(&f, |i| if i < 3 { f.borrow_mut().to_node_idx(Node(i as Variable, 1, 0)) } else { 0 });
+        let aiger = Aiger::from_word(&f.borrow(), "w", &word);
+        assert_eq!(aiger.outputs().len(), 32);
+        let mut out = Vec::new();
+        aiger.write_binary(&mut out, &VariableNames::new()).unwrap();
+        let circuit = read_aiger(&mut f.borrow_mut(), &out[..], &VariableNames::new()).unwrap();
+        assert_eq!(circuit.word(&f, "w"), word);
+
+        let latch = "aag 1 0 1 0 0\n2 3\n";
+        let result = read_aiger(&mut f.borrow_mut(), latch.as_bytes(), &VariableNames::new());
+        match result {
+            Err(AigerError::Sequential) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn aiger_bad_deltas() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        // One gate 6 over inputs 2 and 4, with deltas that reach below zero
+        // and one that does not end.
+        for deltas in &[vec![0x07, 0x01], vec![0x02, 0x05], vec![0xff; 12]] {
+            let mut data = b"aig 3 2 0 1 1\n6\n".to_vec();
+            data.extend(deltas.iter().cloned());
+            match read_aiger(f, &data[..], &names) {
+                Err(AigerError::Parse(_)) => {}
+                other => panic!("{:?}", other),
+            }
+        }
+
+        let mut data = b"aig 3 2 0 1 1\n6\n".to_vec();
+        data.extend([0x02, 0x02].iter().cloned());
+        let circuit = read_aiger(f, &data[..], &names).unwrap();
+        let x0 = f.to_node_idx(Node(0, 1, 0));
+        let x1 = f.to_node_idx(Node(1, 1, 0));
+        assert_eq!(circuit.outputs, vec![("o0".to_string(), multiply(f, x0, x1))]);
+    }
+}
@@ -0,0 +1,499 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::monomial::{Monomial, monomials};
+use super::ordering::{Lex, MonomialOrdering};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+static LEX: Lex = Lex;
+
+/// Names for printing and parsing variables. Variables without one are
+/// written `x<i>`, or `x(i)` as PolyBoRi and Bosphorus do. Both forms are
+/// read.
+#[derive(Debug, Clone)]
+pub struct VariableNames {
+    names: HashMap<Variable, String>,
+    variables: HashMap<String, Variable>,
+    indexed: bool,
+}
+
+fn is_index(digits: &str) -> bool {
+    !digits.is_empty() && digits.bytes().all(is_digit)
+}
+
+/// The variable a name of the form `x<i>` or `x(i)` stands for.
+fn default_variable(name: &str) -> Option<Variable> {
+    if !name.starts_with('x') {
+        return None;
+    }
+    let index = if name.starts_with("x(") && name.ends_with(')') {
+        &name[2..name.len() - 1]
+    } else {
+        &name[1..]
+    };
+    if is_index(index) { index.parse().ok() } else { None }
+}
+
+impl VariableNames {
+    pub fn new() -> VariableNames {
+        VariableNames {
+            names: HashMap::new(),
+            variables: HashMap::new(),
+            indexed: false,
+        }
+    }
+
+    /// Writes unnamed variables as `x(i)`.
+    pub fn indexed() -> VariableNames {
+        VariableNames { indexed: true, ..VariableNames::new() }
+    }
+
+    /// Names `var`, taking the name from any variable that had it before.
+    /// Returns false and changes nothing if `name` is the default name of
+    /// another variable, which would then print the same.
+    pub fn insert(&mut self, var: Variable, name: &str) -> bool {
+        match default_variable(name) {
+            Some(other) if other != var => return false,
+            _ => {}
+        }
+        if let Some(previous) = self.variables.insert(name.to_string(), var) {
+            if previous != var {
+                self.names.remove(&previous);
+            }
+        }
+        if let Some(old) = self.names.insert(var, name.to_string()) {
+            if old != name {
+                self.variables.remove(&old);
+            }
+        }
+        true
+    }
+
+    pub fn name(&self, var: Variable) -> String {
+        match self.names.get(&var) {
+            Some(name) => name.clone(),
+            None if self.indexed => format!("x({})", var),
+            None => format!("x{}", var),
+        }
+    }
+
+    /// The variable called `name`, either given or of the form `x<i>` or
+    /// `x(i)`.
+    pub fn variable(&self, name: &str) -> Option<Variable> {
+        match self.variables.get(name) {
+            Some(&var) => Some(var),
+            None => default_variable(name),
+        }
+    }
+
+    fn write_monomial(&self, f: &mut fmt::Formatter, m: &Monomial) -> fmt::Result {
+        if m.degree() == 0 {
+            return write!(f, "1");
+        }
+        for (i, &var) in m.variables().iter().enumerate() {
+            if i > 0 { try!(write!(f, "*")) }
+            try!(write!(f, "{}", self.name(var)));
+        }
+        Ok(())
+    }
+}
+
+/// A polynomial in its textual form, `x0*x1 + x3 + 1`, with the terms in
+/// descending order.
+pub struct Anf<'a, O: 'a> {
+    forest: &'a Forest,
+    idx: NodeIdx,
+    names: Option<&'a VariableNames>,
+    ordering: &'a O,
+}
+
+/// Displays `idx` with the default names, in lex order.
+pub fn anf(f: &Forest, idx: NodeIdx) -> Anf<Lex> {
+    Anf {
+        forest: f,
+        idx: idx,
+        names: None,
+        ordering: &LEX,
+    }
+}
+
+impl<'a, O: MonomialOrdering> Anf<'a, O> {
+    pub fn with_names(self, names: &'a VariableNames) -> Anf<'a, O> {
+        Anf { names: Some(names), ..self }
+    }
+
+    pub fn with_ordering<P: MonomialOrdering>(self, ordering: &'a P) -> Anf<'a, P> {
+        Anf {
+            forest: self.forest,
+            idx: self.idx,
+            names: self.names,
+            ordering: ordering,
+        }
+    }
+}
+
+impl<'a, O: MonomialOrdering> fmt::Display for Anf<'a, O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.idx == 0 {
+            return write!(f, "0");
+        }
+
+        let default = VariableNames::new();
+        let names = self.names.unwrap_or(&default);
+        let mut terms: Vec<Monomial> = monomials(self.forest, self.idx).collect();
+        terms.sort_by(|a, b| self.ordering.compare(b, a));
+
+        for (i, m) in terms.iter().enumerate() {
+            if i > 0 { try!(write!(f, " + ")) }
+            try!(names.write_monomial(f, m));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAnfError {
+    /// A character that does not belong at this byte offset.
+    Unexpected(usize),
+    /// The text ended in the middle of an expression.
+    End,
+    UnknownVariable(String),
+}
+
+impl fmt::Display for ParseAnfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseAnfError::Unexpected(position) => write!(f, "unexpected character at {}", position),
+            ParseAnfError::End => write!(f, "unexpected end of polynomial"),
+            ParseAnfError::UnknownVariable(ref name) => write!(f, "unknown variable {:?}", name),
+        }
+    }
+}
+
+impl Error for ParseAnfError {
+    fn description(&self) -> &str {
+        match *self {
+            ParseAnfError::Unexpected(_) => "unexpected character in polynomial",
+            ParseAnfError::End => "unexpected end of polynomial",
+            ParseAnfError::UnknownVariable(_) => "unknown variable in polynomial",
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+    names: &'a VariableNames,
+}
+
+fn is_digit(b: u8) -> bool {
+    b'0' <= b && b <= b'9'
+}
+
+fn is_name(b: u8, first: bool) -> bool {
+    b == b'_' || (b'a' <= b && b <= b'z') || (b'A' <= b && b <= b'Z') || (!first && is_digit(b))
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        while self.position < self.text.len() && (self.text[self.position] as char).is_whitespace() {
+            self.position += 1;
+        }
+        self.text.get(self.position).cloned()
+    }
+
+    fn unexpected(&self) -> ParseAnfError {
+        if self.position < self.text.len() {
+            ParseAnfError::Unexpected(self.position)
+        } else {
+            ParseAnfError::End
+        }
+    }
+
+    fn sum(&mut self, f: &mut Forest) -> Result<NodeIdx, ParseAnfError> {
+        let mut p = try!(self.product(f));
+        while self.peek() == Some(b'+') {
+            self.position += 1;
+            let q = try!(self.product(f));
+            p = add(f, p, q);
+        }
+        Ok(p)
+    }
+
+    fn product(&mut self, f: &mut Forest) -> Result<NodeIdx, ParseAnfError> {
+        let mut p = try!(self.factor(f));
+        while self.peek() == Some(b'*') {
+            self.position += 1;
+            let q = try!(self.factor(f));
+            p = multiply(f, p, q);
+        }
+        Ok(p)
+    }
+
+    fn factor(&mut self, f: &mut Forest) -> Result<NodeIdx, ParseAnfError> {
+        match self.peek() {
+            Some(b'(') => {
+                self.position += 1;
+                let p = try!(self.sum(f));
+                if self.peek() != Some(b')') { return Err(self.unexpected()) }
+                self.position += 1;
+                Ok(p)
+            }
+            Some(b) if is_digit(b) => {
+                while self.position < self.text.len() && is_digit(self.text[self.position]) {
+                    self.position += 1;
+                }
+                // Constants count modulo two.
+                Ok((self.text[self.position - 1] - b'0') as NodeIdx & 1)
+            }
+            Some(b) if is_name(b, true) => {
+                let start = self.position;
+                while self.position < self.text.len() && is_name(self.text[self.position], false) {
+                    self.position += 1;
+                }
+                // An index in parentheses right after the name belongs to it.
+                if self.text.get(self.position) == Some(&b'(') {
+                    let mut end = self.position + 1;
+                    while end < self.text.len() && is_digit(self.text[end]) { end += 1 }
+                    if end > self.position + 1 && self.text.get(end) == Some(&b')') {
+                        self.position = end + 1;
+                    }
+                }
+                let name = String::from_utf8_lossy(&self.text[start..self.position]).into_owned();
+                match self.names.variable(&name) {
+                    Some(var) => Ok(f.to_node_idx(Node(var, 1, 0))),
+                    None => Err(ParseAnfError::UnknownVariable(name)),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+/// Parses a polynomial written with `+`, `*`, parentheses, the constants
+/// and variable names. Products are expanded under the forest's sparsity.
+pub fn parse_anf(f: &mut Forest, text: &str, names: &VariableNames) -> Result<NodeIdx, ParseAnfError> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        position: 0,
+        names: names,
+    };
+    let p = try!(parser.sum(f));
+    match parser.peek() {
+        None => Ok(p),
+        Some(_) => Err(parser.unexpected()),
+    }
+}
+
+#[derive(Debug)]
+pub enum AnfFileError {
+    Io(io::Error),
+    Parse { line: usize, error: ParseAnfError },
+}
+
+impl fmt::Display for AnfFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnfFileError::Io(ref err) => write!(f, "{}", err),
+            AnfFileError::Parse { line, ref error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for AnfFileError {
+    fn description(&self) -> &str {
+        match *self {
+            AnfFileError::Io(ref err) => err.description(),
+            AnfFileError::Parse { ref error, .. } => error.description(),
+        }
+    }
+}
+
+impl From<io::Error> for AnfFileError {
+    fn from(err: io::Error) -> AnfFileError {
+        AnfFileError::Io(err)
+    }
+}
+
+/// Reads equations one line at a time, in the format of PolyBoRi and
+/// Bosphorus: a polynomial per line, blank lines and lines starting with
+/// `c` or `#` ignored.
+pub struct AnfReader<R> {
+    reader: R,
+    names: VariableNames,
+    line: usize,
+    buffer: String,
+}
+
+impl<R: BufRead> AnfReader<R> {
+    pub fn new(reader: R, names: VariableNames) -> AnfReader<R> {
+        AnfReader {
+            reader: reader,
+            names: names,
+            line: 0,
+            buffer: String::new(),
+        }
+    }
+
+    /// The next equation, `None` at the end of the input.
+    pub fn read_polynomial(&mut self, f: &mut Forest) -> Option<Result<NodeIdx, AnfFileError>> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(AnfFileError::Io(err))),
+            }
+            self.line += 1;
+
+            let text = self.buffer.trim();
+            if text.is_empty() || text.starts_with('#') || text == "c" || text.starts_with("c ") {
+                continue;
+            }
+            return Some(parse_anf(f, text, &self.names).map_err(|error| {
+                AnfFileError::Parse { line: self.line, error: error }
+            }));
+        }
+    }
+}
+
+/// Reads every equation of a PolyBoRi or Bosphorus ANF file.
+pub fn read_anf<R: BufRead>(f: &mut Forest, reader: R, names: VariableNames) -> Result<Vec<NodeIdx>, AnfFileError> {
+    let mut reader = AnfReader::new(reader, names);
+    let mut polys = Vec::new();
+    while let Some(p) = reader.read_polynomial(f) {
+        polys.push(try!(p));
+    }
+    Ok(polys)
+}
+
+/// Writes the equations one per line, as `read_anf` reads them.
+pub fn write_anf<W: Write>(f: &Forest,
+                           writer: &mut W,
+                           polys: &[NodeIdx],
+                           names: &VariableNames) -> io::Result<()> {
+    for &p in polys {
+        try!(writeln!(writer, "{}", anf(f, p).with_names(names)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::ordering::DegLex;
+
+    #[test]
+    fn anf_display() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x1x2 = multiply(f, x[1], x[2]);
+        let p = add(f, x1x2, x[0]);
+        let p = add(f, p, x[3]);
+        let p = add(f, p, 1);
+
+        assert_eq!(anf(f, 0).to_string(), "0");
+        assert_eq!(anf(f, 1).to_string(), "1");
+        assert_eq!(anf(f, p).to_string(), "x0 + x1*x2 + x3 + 1");
+        assert_eq!(anf(f, p).with_ordering(&DegLex).to_string(), "x1*x2 + x0 + x3 + 1");
+
+        let mut names = VariableNames::new();
+        names.insert(1, "key");
+        names.insert(2, "iv");
+        assert_eq!(anf(f, p).with_names(&names).to_string(), "x0 + key*iv + x3 + 1");
+        assert_eq!(names.variable("key"), Some(1));
+        assert_eq!(names.variable("x7"), Some(7));
+        assert_eq!(names.variable("x"), None);
+
+        // Moving a name leaves its old variable unnamed, and renaming frees
+        // the old name.
+        names.insert(2, "key");
+        names.insert(2, "key");
+        assert_eq!((names.name(1), names.name(2)), ("x1".to_string(), "key".to_string()));
+        assert_eq!(names.variable("key"), Some(2));
+        assert_eq!(names.variable("iv"), None);
+
+        // Another variable's default name would print x3*x5 as x5*x5.
+        assert!(!names.insert(3, "x5"));
+        assert!(!names.insert(3, "x(5)"));
+        assert!(names.insert(3, "x(3)"));
+        let x5 = f.to_node_idx(Node(5, 1, 0));
+        let x3x5 = multiply(f, x[3], x5);
+        let text = anf(f, x3x5).with_names(&names).to_string();
+        assert_eq!(text, "x(3)*x5");
+        assert_eq!(parse_anf(f, &text, &names), Ok(x3x5));
+    }
+
+    #[test]
+    fn anf_parse() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        let mut lfsr: u32 = 7;
+
+        for _ in 0..20 {
+            let mut p = 0;
+            for _ in 0..5 {
+                lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                let a = f.to_node_idx(Node(((lfsr >> 16) % 6) as Variable, 1, 0));
+                let b = f.to_node_idx(Node(((lfsr >> 24) % 6) as Variable, 1, 0));
+                let term = if lfsr & 0x100 != 0 { multiply(f, a, b) } else { add(f, a, 1) };
+                p = add(f, p, term);
+            }
+            let text = anf(f, p).with_ordering(&DegLex).to_string();
+            assert_eq!(parse_anf(f, &text, &names), Ok(p));
+        }
+
+        let x0 = f.to_node_idx(Node(0, 1, 0));
+        let x1 = f.to_node_idx(Node(1, 1, 0));
+        let x0x1 = multiply(f, x0, x1);
+        let expected = add(f, x0x1, x0);
+        assert_eq!(parse_anf(f, " (x1 + 1) * x0 + 2 + x1*x1*(x0+x0)", &names), Ok(expected));
+
+        let mut names = VariableNames::new();
+        names.insert(0, "k_0");
+        assert_eq!(parse_anf(f, "k_0*x1", &names), Ok(x0x1));
+
+        assert_eq!(parse_anf(f, "x0 + ", &names), Err(ParseAnfError::End));
+        assert_eq!(parse_anf(f, "x0 x1", &names), Err(ParseAnfError::Unexpected(3)));
+        assert_eq!(parse_anf(f, "(x0", &names), Err(ParseAnfError::End));
+        assert_eq!(parse_anf(f, "y + 1", &names), Err(ParseAnfError::UnknownVariable("y".to_string())));
+
+        // x0*x1*x2 is dropped.
+        let f = &mut Forest::with_sparsity(2);
+        let expected = parse_anf(f, "x0*x2 + x1*x2 + x2", &names);
+        assert_eq!(parse_anf(f, "(x0 + 1)*(x1 + x2)*x2", &names), expected);
+    }
+
+    #[test]
+    fn anf_files() {
+        let text = "c Bosphorus style\nx(0)*x(12) + x(3) + 1\n\n# PolyBoRi\nx(3) + x1*(x2 + 1)\nc\n";
+        let f = &mut Forest::new();
+        let polys = read_anf(f, text.as_bytes(), VariableNames::new()).unwrap();
+        let names = VariableNames::new();
+        assert_eq!(polys, vec![parse_anf(f, "x0*x12 + x3 + 1", &names).unwrap(),
+                               parse_anf(f, "x1*x2 + x1 + x3", &names).unwrap()]);
+
+        let mut out = Vec::new();
+        write_anf(f, &mut out, &polys, &VariableNames::indexed()).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(written, "x(0)*x(12) + x(3) + 1\nx(1)*x(2) + x(1) + x(3)\n");
+        assert_eq!(read_anf(f, written.as_bytes(), VariableNames::new()).unwrap(), polys);
+
+        let mut reader = AnfReader::new("x(1)\n\nx(2) +\n".as_bytes(), VariableNames::new());
+        assert!(reader.read_polynomial(f).unwrap().is_ok());
+        match reader.read_polynomial(f) {
+            Some(Err(AnfFileError::Parse { line: 3, error: ParseAnfError::End })) => {}
+            other => panic!("{:?}", other),
+        }
+        assert!(reader.read_polynomial(f).is_none());
+    }
+}
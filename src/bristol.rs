@@ -0,0 +1,378 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::word::Word;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// A gate with its input wires followed by its output wire.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Gate {
+    Xor(usize, usize, usize),
+    And(usize, usize, usize),
+    Inv(usize, usize),
+    /// Sets the wire to a constant.
+    Eq(bool, usize),
+    /// Copies a wire.
+    Eqw(usize, usize),
+}
+
+/// A circuit in the Bristol Fashion format of the MPC community. Wire `i`
+/// of an input or output value is its bit `i`; the inputs take the first
+/// wires and the outputs the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BristolCircuit {
+    wires: usize,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    gates: Vec<Gate>,
+}
+
+#[derive(Debug)]
+pub enum BristolError {
+    Io(io::Error),
+    /// A token that does not belong, with its line.
+    Parse { line: usize, token: String },
+    /// A wire out of range or read before it is set.
+    Wire { line: usize, wire: usize },
+}
+
+impl fmt::Display for BristolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BristolError::Io(ref err) => write!(f, "{}", err),
+            BristolError::Parse { line, ref token } => write!(f, "line {}: unexpected {:?}", line, token),
+            BristolError::Wire { line, wire } => write!(f, "line {}: wire {} is not set", line, wire),
+        }
+    }
+}
+
+impl Error for BristolError {
+    fn description(&self) -> &str {
+        match *self {
+            BristolError::Io(ref err) => err.description(),
+            BristolError::Parse { .. } => "malformed Bristol Fashion circuit",
+            BristolError::Wire { .. } => "Bristol Fashion wire used before it is set",
+        }
+    }
+}
+
+impl From<io::Error> for BristolError {
+    fn from(err: io::Error) -> BristolError {
+        BristolError::Io(err)
+    }
+}
+
+fn numbers(line: usize, tokens: &[&str]) -> Result<Vec<usize>, BristolError> {
+    let mut numbers = Vec::new();
+    for token in tokens {
+        match token.parse() {
+            Ok(n) => numbers.push(n),
+            Err(_) => return Err(BristolError::Parse { line: line, token: token.to_string() }),
+        }
+    }
+    Ok(numbers)
+}
+
+/// The counts after the number of values on a header line.
+fn sizes(line: usize, tokens: &[&str]) -> Result<Vec<usize>, BristolError> {
+    let counts = try!(numbers(line, tokens));
+    if counts.is_empty() || counts.len() - 1 != counts[0] {
+        return Err(BristolError::Parse { line: line, token: tokens.join(" ") });
+    }
+    Ok(counts[1..].to_vec())
+}
+
+/// The total number of bits of some values.
+fn total(line: usize, sizes: &[usize]) -> Result<usize, BristolError> {
+    match sizes.iter().fold(Some(0), |sum: Option<usize>, &n| sum.and_then(|sum| sum.checked_add(n))) {
+        Some(bits) => Ok(bits),
+        None => Err(BristolError::Parse { line: line, token: format!("{:?}", sizes) }),
+    }
+}
+
+/// Reads a Bristol Fashion circuit, checking that every wire is set before
+/// it is used. `MAND` gates are split into their `AND`s.
+///
+/// The counts in the header are not trusted: wires are only tracked as
+/// gates set them, and a circuit with more wires than its inputs and gate
+/// outputs is rejected, so that `evaluate` allocates no more than the file
+/// describes.
+pub fn read_bristol<R: BufRead>(reader: R) -> Result<BristolCircuit, BristolError> {
+    let mut header: Vec<Vec<usize>> = Vec::new();
+    let mut circuit = BristolCircuit {
+        wires: 0,
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        gates: Vec::new(),
+    };
+    // Gate outputs; the input wires are set from the start.
+    let mut set: HashSet<usize> = HashSet::new();
+    let mut input_bits = 0;
+    let mut gate_lines = 0;
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = try!(line);
+        let number = number + 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() { continue }
+
+        match header.len() {
+            0 => {
+                let counts = try!(numbers(number, &tokens));
+                if counts.len() != 2 { return Err(BristolError::Parse { line: number, token: line.clone() }) }
+                circuit.wires = counts[1];
+                header.push(counts);
+                continue;
+            }
+            1 => {
+                circuit.inputs = try!(sizes(number, &tokens));
+                input_bits = try!(total(number, &circuit.inputs));
+                if input_bits > circuit.wires { return Err(BristolError::Wire { line: number, wire: input_bits }) }
+                header.push(circuit.inputs.clone());
+                continue;
+            }
+            2 => {
+                circuit.outputs = try!(sizes(number, &tokens));
+                header.push(circuit.outputs.clone());
+                continue;
+            }
+            _ => {}
+        }
+
+        if tokens.len() < 4 { return Err(BristolError::Parse { line: number, token: line.clone() }) }
+        let op = tokens[tokens.len() - 1];
+        let counts = try!(numbers(number, &tokens[..2]));
+        let wires = try!(numbers(number, &tokens[2..tokens.len() - 1]));
+        if counts[0].checked_add(counts[1]) != Some(wires.len()) {
+            return Err(BristolError::Parse { line: number, token: line.clone() });
+        }
+        let (ins, outs) = wires.split_at(counts[0]);
+
+        let gates = match (op, ins.len(), outs.len()) {
+            ("XOR", 2, 1) => vec![Gate::Xor(ins[0], ins[1], outs[0])],
+            ("AND", 2, 1) => vec![Gate::And(ins[0], ins[1], outs[0])],
+            ("INV", 1, 1) => vec![Gate::Inv(ins[0], outs[0])],
+            ("EQW", 1, 1) => vec![Gate::Eqw(ins[0], outs[0])],
+            ("EQ", 1, 1) if ins[0] < 2 => {
+                // The constant is not a wire.
+                if outs[0] >= circuit.wires { return Err(BristolError::Wire { line: number, wire: outs[0] }) }
+                set.insert(outs[0]);
+                circuit.gates.push(Gate::Eq(ins[0] == 1, outs[0]));
+                gate_lines += 1;
+                continue;
+            }
+            ("MAND", n, m) if n == 2 * m => (0..m).map(|i| Gate::And(ins[i], ins[m + i], outs[i])).collect(),
+            _ => return Err(BristolError::Parse { line: number, token: op.to_string() }),
+        };
+
+        for &wire in ins {
+            if wire >= circuit.wires || (wire >= input_bits && !set.contains(&wire)) {
+                return Err(BristolError::Wire { line: number, wire: wire });
+            }
+        }
+        for &wire in outs {
+            if wire >= circuit.wires { return Err(BristolError::Wire { line: number, wire: wire }) }
+            set.insert(wire);
+        }
+        circuit.gates.extend(gates);
+        gate_lines += 1;
+    }
+
+    if header.len() < 3 {
+        return Err(BristolError::Parse { line: 0, token: String::new() });
+    }
+    if gate_lines != header[0][0] {
+        return Err(BristolError::Parse { line: 0, token: format!("{} gates", gate_lines) });
+    }
+    if circuit.wires - input_bits > set.iter().filter(|&&wire| wire >= input_bits).count() {
+        return Err(BristolError::Parse { line: 1, token: format!("{} wires", circuit.wires) });
+    }
+    let bits = try!(total(3, &circuit.outputs));
+    if bits > circuit.wires {
+        return Err(BristolError::Wire { line: 0, wire: bits });
+    }
+    for wire in circuit.wires - bits..circuit.wires {
+        if wire >= input_bits && !set.contains(&wire) { return Err(BristolError::Wire { line: 0, wire: wire }) }
+    }
+    Ok(circuit)
+}
+
+impl BristolCircuit {
+    /// The bit width of each input value.
+    pub fn inputs(&self) -> &[usize] {
+        &self.inputs
+    }
+
+    /// The bit width of each output value.
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// The variables `first`, `first + 1`, ... as the bits of input
+    /// `value`, for a block of unknowns.
+    pub fn variables(&self, f: &mut Forest, value: usize, first: Variable) -> Vec<NodeIdx> {
+        let bits = self.inputs[value];
+        assert!(first as usize + bits <= 256, "input {} needs {} variables from {}", value, bits, first);
+        (0..bits).map(|i| f.to_node_idx(Node(first + i as Variable, 1, 0))).collect()
+    }
+
+    /// Runs the circuit on polynomials, one vector of bits per input
+    /// value, and returns the bits of each output value.
+    pub fn evaluate(&self, f: &mut Forest, inputs: &[Vec<NodeIdx>]) -> Vec<Vec<NodeIdx>> {
+        assert_eq!(inputs.len(), self.inputs.len());
+        let mut values: Vec<NodeIdx> = vec![0; self.wires];
+        let mut wire = 0;
+        for (bits, &size) in inputs.iter().zip(self.inputs.iter()) {
+            assert_eq!(bits.len(), size);
+            for &bit in bits {
+                values[wire] = bit;
+                wire += 1;
+            }
+        }
+
+        for gate in &self.gates {
+            match *gate {
+                Gate::Xor(a, b, out) => values[out] = add(f, values[a], values[b]),
+                Gate::And(a, b, out) => values[out] = multiply(f, values[a], values[b]),
+                Gate::Inv(a, out) => values[out] = add(f, values[a], 1),
+                Gate::Eq(constant, out) => values[out] = constant as NodeIdx,
+                Gate::Eqw(a, out) => values[out] = values[a],
+            }
+        }
+
+        let mut wire = self.wires - self.outputs.iter().fold(0, |sum, &n| sum + n);
+        self.outputs.iter().map(|&size| {
+            wire += size;
+            values[wire - size..wire].to_vec()
+        }).collect()
+    }
+
+    /// As `evaluate`, with values as words: every 32 wires of a value make
+    /// one word, the last padded with zeros.
+    pub fn evaluate_words<'a>(&self, forest: &'a RefCell<Forest>, inputs: &[Vec<Word<'a>>]) -> Vec<Vec<Word<'a>>> {
+        let inputs: Vec<Vec<NodeIdx>> = inputs.iter().zip(self.inputs.iter()).map(|(words, &size)| {
+            (0..size).map(|i| words[i / 32].get_bit(i % 32)).collect()
+        }).collect();
+        let outputs = self.evaluate(&mut forest.borrow_mut(), &inputs);
+        outputs.iter().map(|bits| {
+            bits.chunks(32).map(|chunk| Word::from_fn(forest, |i| chunk.get(i).cloned().unwrap_or(0))).collect()
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::word::Word;
+
+    use std::cell::RefCell;
+    use std::usize;
+
+    // Two bit addition of inputs 0, 1 and 2, 3 into outputs 7, 8, and the
+    // negated carry and constant one as a second output.
+    const ADDER: &'static str = "7 11\n2 2 2\n2 2 2\n\n\
+                                 2 1 0 2 4 AND\n2 1 1 3 5 XOR\n2 1 5 4 6 XOR\n\
+                                 1 1 6 9 INV\n1 1 1 10 EQ\n2 1 0 2 7 XOR\n1 1 6 8 EQW\n";
+
+    #[test]
+    fn bristol_adder() {
+        let circuit = read_bristol(ADDER.as_bytes()).unwrap();
+        assert_eq!(circuit.inputs(), &[2, 2]);
+        assert_eq!(circuit.outputs(), &[2, 2]);
+
+        let f = &mut Forest::new();
+        for a in 0..4 {
+            for b in 0..4 {
+                let bits = |n: usize| vec![n & 1, (n >> 1) & 1];
+                let out = circuit.evaluate(f, &[bits(a), bits(b)]);
+                let sum = (a + b) % 4;
+                assert_eq!(out[0], bits(sum));
+                assert_eq!(out[1], vec![1 - ((sum >> 1) & 1), 1]);
+            }
+        }
+
+        let a = circuit.variables(f, 0, 0);
+        let b = circuit.variables(f, 1, 2);
+        let out = circuit.evaluate(f, &[a.clone(), b.clone()]);
+        let carry = multiply(f, a[0], b[0]);
+        let high = add(f, a[1], b[1]);
+        let high = add(f, high, carry);
+        assert_eq!(out[0], vec![add(f, a[0], b[0]), high]);
+    }
+
+    #[test]
+    fn bristol_words() {
+        // 64 bit XOR and AND of two values, as MAND for the latter.
+        let mut text = String::from("129 320\n2 64 64\n2 64 64\n");
+        for i in 0..64 {
+            text.push_str(&format!("2 1 {} {} {} XOR\n", i, 64 + i, 192 + i));
+        }
+        let ins: Vec<String> = (0..128).map(|i| i.to_string()).collect();
+        let outs: Vec<String> = (256..320).map(|i| i.to_string()).collect();
+        text.push_str(&format!("128 64 {} {} MAND\n", ins.join(" "), outs.join(" ")));
+        for i in 0..63 {
+            text.push_str(&format!("1 1 {} {} EQW\n", i, 128 + i));
+        }
+        // One gate line short.
+        assert!(read_bristol(text.as_bytes()).is_err());
+        text.push_str("1 1 0 191 EQ\n");
+        let circuit = read_bristol(text.as_bytes()).unwrap();
+
+        let forest = RefCell::new(Forest::new());
+        let a: Vec<Word> = (0..2).map(|w| Word::from_fn(&forest, |i| {
+            forest.borrow_mut().to_node_idx(Node((w * 32 + i) as Variable, 1, 0))
+        })).collect();
+        let b = vec![Word::constant(&forest, 0xdeadbeef), Word::constant(&forest, 0x0f0f0f0f)];
+        let out = circuit.evaluate_words(&forest, &[a.clone(), b.clone()]);
+        assert_eq!(out[0], vec![&a[0] ^ &b[0], &a[1] ^ &b[1]]);
+        assert_eq!(out[1], vec![&a[0] & &b[0], &a[1] & &b[1]]);
+    }
+
+    #[test]
+    fn bristol_errors() {
+        match read_bristol("1 3\n1 1\n1 1\n2 1 0 1 2 AND\n".as_bytes()) {
+            Err(BristolError::Wire { line: 4, wire: 1 }) => {}
+            other => panic!("{:?}", other),
+        }
+        match read_bristol("1 3\n1 2\n1 1\n2 1 0 1 2 NAND\n".as_bytes()) {
+            Err(BristolError::Parse { line: 4, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+
+        // Hostile counts fail without allocating or overflowing.
+        match read_bristol("1 99999999999999\n1 1\n1 1\n1 1 0 1 INV\n".as_bytes()) {
+            Err(BristolError::Parse { line: 1, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+        let huge = usize::MAX.to_string();
+        let text = format!("1 3\n1 1\n1 1\n{} 1 0 1 2 AND\n", huge);
+        match read_bristol(text.as_bytes()) {
+            Err(BristolError::Parse { line: 4, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+        let text = format!("1 3\n{} 1\n1 1\n1 1 0 1 INV\n", huge);
+        match read_bristol(text.as_bytes()) {
+            Err(BristolError::Parse { line: 2, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+        let text = format!("1 3\n2 {} 1\n1 1\n1 1 0 1 INV\n", huge);
+        match read_bristol(text.as_bytes()) {
+            Err(BristolError::Parse { line: 2, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
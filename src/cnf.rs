@@ -0,0 +1,642 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::monomial::{Monomial, monomials, support};
+use super::monomial_count::monomial_count;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// DIMACS literal: a CNF variable, negative if negated.
+pub type Literal = isize;
+
+/// The most variables a truth table or an expanded XOR may have. Larger
+/// options are clamped to it.
+const TABLE_LIMIT: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct CnfOptions {
+    /// XORs longer than this are cut into chunks joined by new variables.
+    /// At most 16.
+    pub cutting_number: usize,
+    /// Non-linear polynomials with at most this many variables are encoded
+    /// from their truth table instead. At most 16.
+    pub karnaugh_limit: usize,
+    /// Keep XORs whole as CryptoMiniSat `x` clauses rather than expanding
+    /// them.
+    pub xor_clauses: bool,
+}
+
+impl CnfOptions {
+    pub fn new() -> CnfOptions {
+        CnfOptions {
+            cutting_number: 5,
+            karnaugh_limit: 8,
+            xor_clauses: false,
+        }
+    }
+}
+
+/// What a CNF variable stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CnfVariable {
+    Variable(Variable),
+    /// The product of these variables.
+    Monomial(Monomial),
+    /// The sum of these CNF variables, from cutting a long XOR.
+    Cut(Vec<usize>),
+}
+
+/// Clauses over CNF variables `1..`, where variable `i` is described by
+/// `variables()[i - 1]`.
+#[derive(Debug, Clone)]
+pub struct Cnf {
+    variables: Vec<CnfVariable>,
+    clauses: Vec<Vec<Literal>>,
+    xors: Vec<(Vec<usize>, bool)>,
+    indices: HashMap<Variable, usize>,
+    products: HashMap<Monomial, usize>,
+}
+
+fn parity(x: u32) -> bool {
+    x.count_ones() & 1 == 1
+}
+
+/// The prime implicants of a function given by its true points, as cubes
+/// `(bits, free)` covering every point that agrees with `bits` outside
+/// `free` (Quine-McCluskey).
+fn prime_implicants(points: &[u32], width: usize) -> Vec<(u32, u32)> {
+    let mut cubes: Vec<(u32, u32)> = points.iter().map(|&x| (x, 0)).collect();
+    let mut primes = Vec::new();
+
+    while !cubes.is_empty() {
+        let set: HashSet<(u32, u32)> = cubes.iter().cloned().collect();
+        let mut merged: HashSet<(u32, u32)> = HashSet::new();
+        let mut next = Vec::new();
+
+        for &(bits, free) in &cubes {
+            for i in 0..width {
+                let bit = 1 << i;
+                if free & bit != 0 { continue }
+                let other = (bits ^ bit, free);
+                if set.contains(&other) {
+                    merged.insert((bits, free));
+                    if bits & bit == 0 {
+                        next.push((bits, free | bit));
+                    }
+                }
+            }
+        }
+
+        primes.extend(cubes.iter().cloned().filter(|cube| !merged.contains(cube)));
+        next.sort();
+        next.dedup();
+        cubes = next;
+    }
+
+    primes
+}
+
+/// A small set of cubes covering `points`, picked greedily from the prime
+/// implicants.
+fn cover(points: &[u32], width: usize) -> Vec<(u32, u32)> {
+    let primes = prime_implicants(points, width);
+    let mut uncovered: HashSet<u32> = points.iter().cloned().collect();
+    let mut cubes = Vec::new();
+
+    while !uncovered.is_empty() {
+        let best = {
+            let count = |&(bits, free): &(u32, u32)| {
+                uncovered.iter().filter(|&&x| x & !free == bits).count()
+            };
+            let mut best = primes[0];
+            for prime in &primes {
+                if count(prime) > count(&best) { best = *prime }
+            }
+            best
+        };
+        uncovered.retain(|&x| x & !best.1 != best.0);
+        cubes.push(best);
+    }
+
+    cubes
+}
+
+impl Cnf {
+    pub fn new() -> Cnf {
+        Cnf {
+            variables: Vec::new(),
+            clauses: Vec::new(),
+            xors: Vec::new(),
+            indices: HashMap::new(),
+            products: HashMap::new(),
+        }
+    }
+
+    /// Encodes the equations `p = 0`.
+    pub fn from_polynomials(f: &Forest, polys: &[NodeIdx], options: &CnfOptions) -> Cnf {
+        let mut cnf = Cnf::new();
+        for &p in polys {
+            cnf.add_polynomial(f, p, options);
+        }
+        cnf
+    }
+
+    pub fn variables(&self) -> &[CnfVariable] {
+        &self.variables
+    }
+
+    pub fn clauses(&self) -> &[Vec<Literal>] {
+        &self.clauses
+    }
+
+    /// XOR clauses, each the variables summed and the value of the sum.
+    /// Only used with `xor_clauses`.
+    pub fn xors(&self) -> &[(Vec<usize>, bool)] {
+        &self.xors
+    }
+
+    /// The CNF variable of `var`, if it occurs.
+    pub fn variable(&self, var: Variable) -> Option<usize> {
+        self.indices.get(&var).cloned()
+    }
+
+    fn push_variable(&mut self, variable: CnfVariable) -> usize {
+        self.variables.push(variable);
+        self.variables.len()
+    }
+
+    fn index(&mut self, var: Variable) -> usize {
+        if let Some(&index) = self.indices.get(&var) {
+            return index;
+        }
+        let index = self.push_variable(CnfVariable::Variable(var));
+        self.indices.insert(var, index);
+        index
+    }
+
+    /// A variable equal to the monomial, defined by `t -> x` for each of its
+    /// variables and `x1 & .. & xk -> t`.
+    fn product(&mut self, m: &Monomial) -> usize {
+        if m.degree() == 1 {
+            return self.index(m.variables()[0]);
+        }
+        if let Some(&index) = self.products.get(m) {
+            return index;
+        }
+
+        let vars: Vec<usize> = m.variables().iter().map(|&var| self.index(var)).collect();
+        let t = self.push_variable(CnfVariable::Monomial(m.clone()));
+        self.products.insert(m.clone(), t);
+
+        let mut definition = vec![t as Literal];
+        for &x in &vars {
+            self.clauses.push(vec![-(t as Literal), x as Literal]);
+            definition.push(-(x as Literal));
+        }
+        self.clauses.push(definition);
+        t
+    }
+
+    /// Forbids every assignment of `vars` whose sum is not `value`.
+    fn expand_xor(&mut self, vars: &[usize], value: bool) {
+        for x in 0..1u32 << vars.len() {
+            if parity(x) == value { continue }
+            self.clauses.push(vars.iter().enumerate().map(|(i, &var)| {
+                if x >> i & 1 == 1 { -(var as Literal) } else { var as Literal }
+            }).collect());
+        }
+    }
+
+    fn add_xor(&mut self, mut vars: Vec<usize>, value: bool, options: &CnfOptions) {
+        if options.xor_clauses && !vars.is_empty() {
+            self.xors.push((vars, value));
+            return;
+        }
+
+        let cutting_number = options.cutting_number.max(3).min(TABLE_LIMIT);
+        while vars.len() > cutting_number {
+            let rest = vars.split_off(cutting_number - 1);
+            let cut = self.push_variable(CnfVariable::Cut(vars.clone()));
+            vars.push(cut);
+            self.expand_xor(&vars, false);
+            vars = Some(cut).into_iter().chain(rest).collect();
+        }
+        self.expand_xor(&vars, value);
+    }
+
+    /// Blocks the points where `p` is one, merged into as few clauses as the
+    /// prime implicants allow.
+    fn add_karnaugh(&mut self, f: &Forest, p: NodeIdx, vars: &[Variable]) {
+        let terms: Vec<u32> = monomials(f, p).map(|m| {
+            m.variables().iter().fold(0, |mask, var| {
+                mask | 1 << vars.iter().position(|v| v == var).unwrap()
+            })
+        }).collect();
+        let points: Vec<u32> = (0..1u32 << vars.len())
+            .filter(|&x| terms.iter().filter(|&&mask| mask & !x == 0).count() & 1 == 1)
+            .collect();
+        if points.is_empty() { return }
+
+        let indices: Vec<usize> = vars.iter().map(|&var| self.index(var)).collect();
+        for (bits, free) in cover(&points, vars.len()) {
+            self.clauses.push((0..vars.len()).filter(|&i| free >> i & 1 == 0).map(|i| {
+                if bits >> i & 1 == 1 { -(indices[i] as Literal) } else { indices[i] as Literal }
+            }).collect());
+        }
+    }
+
+    /// Adds the equation `p = 0`.
+    pub fn add_polynomial(&mut self, f: &Forest, p: NodeIdx, options: &CnfOptions) {
+        if p == 0 { return }
+
+        let vars: Vec<Variable> = support(f, p).collect();
+        if f.degree(p) > 1 && vars.len() <= options.karnaugh_limit.min(TABLE_LIMIT) {
+            return self.add_karnaugh(f, p, &vars);
+        }
+
+        let mut sum = Vec::new();
+        let mut value = false;
+        for m in monomials(f, p) {
+            if m.degree() == 0 {
+                value = !value;
+            } else {
+                sum.push(self.product(&m));
+            }
+        }
+        self.add_xor(sum, value, options);
+    }
+
+    /// Writes the clauses in DIMACS format, preceded by `c map` comments that
+    /// give the variable or monomial behind each CNF variable.
+    pub fn write_dimacs<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (i, variable) in self.variables.iter().enumerate() {
+            match *variable {
+                CnfVariable::Variable(var) => try!(writeln!(writer, "c map {} x{}", i + 1, var)),
+                CnfVariable::Monomial(ref m) => {
+                    let names: Vec<String> = m.variables().iter().map(|var| format!("x{}", var)).collect();
+                    try!(writeln!(writer, "c map {} {}", i + 1, names.join("*")));
+                }
+                CnfVariable::Cut(_) => {}
+            }
+        }
+
+        try!(writeln!(writer, "p cnf {} {}", self.variables.len(), self.clauses.len() + self.xors.len()));
+        for clause in &self.clauses {
+            for &literal in clause {
+                try!(write!(writer, "{} ", literal));
+            }
+            try!(writeln!(writer, "0"));
+        }
+
+        // `x1 2 0` states that the sum is one, a negated literal flips it.
+        for &(ref vars, value) in &self.xors {
+            try!(write!(writer, "x"));
+            for (i, &var) in vars.iter().enumerate() {
+                let literal = if i == 0 && !value { -(var as Literal) } else { var as Literal };
+                try!(write!(writer, "{} ", literal));
+            }
+            try!(writeln!(writer, "0"));
+        }
+        Ok(())
+    }
+
+    /// The true variables of a model given as DIMACS literals.
+    pub fn decode(&self, model: &[Literal]) -> HashSet<Variable> {
+        model.iter()
+             .filter(|&&literal| literal > 0)
+             .filter_map(|&literal| match self.variables.get(literal as usize - 1) {
+                 Some(&CnfVariable::Variable(var)) => Some(var),
+                 _ => None,
+             })
+             .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DimacsOptions {
+    /// Clauses sharing variables are joined into one equation while it has
+    /// at most this many monomials.
+    pub group_monomials: usize,
+}
+
+impl DimacsOptions {
+    pub fn new() -> DimacsOptions {
+        DimacsOptions { group_monomials: 16 }
+    }
+}
+
+#[derive(Debug)]
+pub enum DimacsError {
+    Io(io::Error),
+    /// A token that is not a literal, with its line.
+    Parse { line: usize, token: String },
+    /// Only CNF variables `1` to `256` have a `Variable`.
+    Variable { line: usize, variable: usize },
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DimacsError::Io(ref err) => write!(f, "{}", err),
+            DimacsError::Parse { line, ref token } => write!(f, "line {}: bad literal {:?}", line, token),
+            DimacsError::Variable { line, variable } => {
+                write!(f, "line {}: variable {} is above 256", line, variable)
+            }
+        }
+    }
+}
+
+impl Error for DimacsError {
+    fn description(&self) -> &str {
+        match *self {
+            DimacsError::Io(ref err) => err.description(),
+            DimacsError::Parse { .. } => "malformed DIMACS literal",
+            DimacsError::Variable { .. } => "DIMACS variable out of range",
+        }
+    }
+}
+
+impl From<io::Error> for DimacsError {
+    fn from(err: io::Error) -> DimacsError {
+        DimacsError::Io(err)
+    }
+}
+
+/// One for the assignments that make the literal false.
+fn falsified(f: &mut Forest, literal: Literal) -> NodeIdx {
+    let x = f.to_node_idx(Node((literal.abs() - 1) as Variable, 1, 0));
+    if literal > 0 { add(f, x, 1) } else { x }
+}
+
+/// Reads DIMACS CNF into equations, CNF variable `i` becoming `Variable`
+/// `i - 1`. A clause is zero where it holds, as the product of its
+/// falsified literals; an `x` clause states that the sum of its literals is
+/// one. Clauses are joined with the ones before while that stays within
+/// `group_monomials`, since `a = 0, b = 0` is `a + b + ab = 0`.
+pub fn read_dimacs<R: BufRead>(f: &mut Forest,
+                               reader: R,
+                               options: &DimacsOptions) -> Result<Vec<NodeIdx>, DimacsError> {
+    let mut polys = Vec::new();
+    let mut group: Option<(NodeIdx, HashSet<Literal>)> = None;
+    let mut clause: Vec<Literal> = Vec::new();
+    let mut xor = false;
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = try!(line);
+        let line = line.trim();
+        if line.starts_with('c') || line.starts_with('p') { continue }
+        if line.starts_with('%') { break }
+
+        for token in line.split_whitespace() {
+            let token = if clause.is_empty() && token.starts_with('x') {
+                xor = true;
+                &token[1..]
+            } else {
+                token
+            };
+            if token.is_empty() { continue }
+
+            let literal: Literal = match token.parse() {
+                Ok(literal) => literal,
+                Err(_) => return Err(DimacsError::Parse { line: number + 1, token: token.to_string() }),
+            };
+            // abs() overflows on the most negative literal, whose magnitude
+            // wrapping_abs() still gets right as a usize.
+            if literal < -256 || literal > 256 {
+                return Err(DimacsError::Variable { line: number + 1, variable: literal.wrapping_abs() as usize });
+            }
+            if literal != 0 {
+                clause.push(literal);
+                continue;
+            }
+
+            if xor {
+                let p = clause.iter().fold(1, |p, &literal| {
+                    let x = falsified(f, -literal);
+                    add(f, p, x)
+                });
+                polys.push(p);
+            } else {
+                let p = clause.iter().fold(1, |p, &literal| {
+                    let x = falsified(f, literal);
+                    multiply(f, p, x)
+                });
+                let vars: HashSet<Literal> = clause.iter().map(|literal| literal.abs()).collect();
+
+                group = match group.take() {
+                    Some((g, mut group_vars)) => {
+                        let joined = if vars.iter().any(|var| group_vars.contains(var)) {
+                            let gp = multiply(f, g, p);
+                            let sum = add(f, g, p);
+                            let joined = add(f, sum, gp);
+                            if monomial_count(f, joined) <= options.group_monomials { Some(joined) } else { None }
+                        } else {
+                            None
+                        };
+                        match joined {
+                            Some(joined) => {
+                                group_vars.extend(vars);
+                                Some((joined, group_vars))
+                            }
+                            None => {
+                                polys.push(g);
+                                Some((p, vars))
+                            }
+                        }
+                    }
+                    None => Some((p, vars)),
+                };
+            }
+            clause.clear();
+            xor = false;
+        }
+    }
+
+    if let Some((g, _)) = group {
+        polys.push(g);
+    }
+    Ok(polys)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    use std::collections::HashSet;
+    use std::isize;
+
+    /// Extends an assignment of the original variables to the CNF variables,
+    /// which are all determined by it.
+    fn extend(cnf: &Cnf, solution: &HashSet<Variable>) -> Vec<bool> {
+        let mut values: Vec<bool> = Vec::new();
+        for variable in cnf.variables() {
+            let value = match *variable {
+                CnfVariable::Variable(var) => solution.contains(&var),
+                CnfVariable::Monomial(ref m) => m.variables().iter().all(|var| solution.contains(var)),
+                CnfVariable::Cut(ref vars) => vars.iter().fold(false, |sum, &var| sum ^ values[var - 1]),
+            };
+            values.push(value);
+        }
+        values
+    }
+
+    fn satisfied(cnf: &Cnf, values: &[bool]) -> bool {
+        let value = |literal: Literal| values[literal.abs() as usize - 1] == (literal > 0);
+        cnf.clauses().iter().all(|clause| clause.iter().any(|&literal| value(literal))) &&
+        cnf.xors().iter().all(|&(ref vars, sum)| {
+            vars.iter().fold(false, |acc, &var| acc ^ values[var - 1]) == sum
+        })
+    }
+
+    #[test]
+    fn cnf_matches_polynomials() {
+        let mut lfsr: u32 = 5;
+
+        for &(karnaugh_limit, xor_clauses) in &[(0, false), (8, false), (4, false), (0, true)] {
+            let f = &mut Forest::new();
+            let polys: Vec<NodeIdx> = (0..6).map(|i| {
+                let mut p = if i == 0 { 1 } else { 0 };
+                for _ in 0..3 + i {
+                    lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let a = f.to_node_idx(Node(((lfsr >> 16) % 7) as Variable, 1, 0));
+                    let b = f.to_node_idx(Node(((lfsr >> 24) % 7) as Variable, 1, 0));
+                    let term = if lfsr & 0x100 != 0 { multiply(f, a, b) } else { a };
+                    p = add(f, p, term);
+                }
+                p
+            }).collect();
+
+            let options = CnfOptions {
+                cutting_number: 3,
+                karnaugh_limit: karnaugh_limit,
+                xor_clauses: xor_clauses,
+            };
+            let cnf = Cnf::from_polynomials(f, &polys, &options);
+            assert_eq!(cnf.xors().is_empty(), !xor_clauses);
+
+            for mask in 0..128u32 {
+                let solution: HashSet<Variable> = (0..7).filter(|&i| mask >> i & 1 == 1).collect();
+                let expected = polys.iter().all(|&p| !f.evaluate(p, &solution));
+                assert_eq!(satisfied(&cnf, &extend(&cnf, &solution)), expected);
+
+                let model: Vec<Literal> = extend(&cnf, &solution).iter().enumerate()
+                    .map(|(i, &value)| if value { i as Literal + 1 } else { -(i as Literal) - 1 })
+                    .collect();
+                let decoded: HashSet<Variable> = solution.iter()
+                    .cloned()
+                    .filter(|&var| cnf.variable(var).is_some())
+                    .collect();
+                assert_eq!(cnf.decode(&model), decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn cnf_limits() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..33).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let p = x[2..].iter().fold(x0x1, |p, &x| add(f, p, x));
+
+        // Both limits are past what a 32 bit truth table holds.
+        let options = CnfOptions {
+            cutting_number: 64,
+            karnaugh_limit: 64,
+            xor_clauses: false,
+        };
+        let cnf = Cnf::from_polynomials(f, &[p], &options);
+        assert!(cnf.clauses().iter().all(|clause| clause.len() <= 16));
+
+        let mut lfsr: u32 = 7;
+        for _ in 0..32 {
+            lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+            let solution: HashSet<Variable> = (0..33).filter(|&i| (lfsr >> (i % 29)) & 1 == 1).collect();
+            assert_eq!(satisfied(&cnf, &extend(&cnf, &solution)), !f.evaluate(p, &solution));
+        }
+    }
+
+    #[test]
+    fn cnf_dimacs() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..3).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let p = add(f, x0x1, x[2]);
+        let q = add(f, x[0], x[2]);
+
+        let mut options = CnfOptions::new();
+        options.karnaugh_limit = 0;
+        options.xor_clauses = true;
+        let cnf = Cnf::from_polynomials(f, &[p, q, 1], &options);
+
+        let mut out = Vec::new();
+        cnf.write_dimacs(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "c map 1 x0\nc map 2 x1\nc map 3 x0*x1\nc map 4 x2\n\
+                    p cnf 4 6\n-3 1 0\n-3 2 0\n3 -1 -2 0\n0\nx-3 4 0\nx-1 4 0\n");
+    }
+
+    #[test]
+    fn cnf_read_dimacs() {
+        let text = "c example\np cnf 4 5\n1 -2 0\n2 3\n -4 0\nx1 2 -4 0\n-1 -3 0\nx 3 0\n%\n0\n";
+        let clauses: Vec<(Vec<Literal>, bool)> = vec![(vec![1, -2], false), (vec![2, 3, -4], false),
+                                                      (vec![1, 2, -4], true), (vec![-1, -3], false),
+                                                      (vec![3], true)];
+
+        let mut counts = Vec::new();
+        for &group_monomials in &[0, 64] {
+            let f = &mut Forest::new();
+            let options = DimacsOptions { group_monomials: group_monomials };
+            let polys = read_dimacs(f, text.as_bytes(), &options).unwrap();
+            counts.push(polys.len());
+
+            for mask in 0..16u32 {
+                let solution: HashSet<Variable> = (0..4).filter(|&i| mask >> i & 1 == 1).collect();
+                let value = |literal: Literal| solution.contains(&((literal.abs() - 1) as Variable)) == (literal > 0);
+                let expected = clauses.iter().all(|&(ref clause, xor)| {
+                    if xor {
+                        clause.iter().fold(false, |sum, &literal| sum ^ value(literal))
+                    } else {
+                        clause.iter().any(|&literal| value(literal))
+                    }
+                });
+                assert_eq!(polys.iter().all(|&p| !f.evaluate(p, &solution)), expected);
+            }
+        }
+        assert_eq!(counts[0], 5);
+        assert!(counts[1] < counts[0]);
+
+        let f = &mut Forest::new();
+        match read_dimacs(f, "1 2 0\n3 a 0\n".as_bytes(), &DimacsOptions::new()) {
+            Err(DimacsError::Parse { line: 2, ref token }) if token == "a" => {}
+            other => panic!("{:?}", other),
+        }
+        match read_dimacs(f, "300 0\n".as_bytes(), &DimacsOptions::new()) {
+            Err(DimacsError::Variable { line: 1, variable: 300 }) => {}
+            other => panic!("{:?}", other),
+        }
+
+        // The last variable there is.
+        let x255 = f.to_node_idx(Node(255, 1, 0));
+        let not_x255 = add(f, x255, 1);
+        let polys = read_dimacs(f, "256 0\n-256 0\n".as_bytes(), &DimacsOptions { group_monomials: 0 }).unwrap();
+        assert_eq!(polys, vec![not_x255, x255]);
+        match read_dimacs(f, "257 0\n".as_bytes(), &DimacsOptions::new()) {
+            Err(DimacsError::Variable { line: 1, variable: 257 }) => {}
+            other => panic!("{:?}", other),
+        }
+        // -9223372036854775808 on 64-bit targets, which has no abs().
+        let text = format!("1 {} 0\n", isize::MIN);
+        match read_dimacs(f, text.as_bytes(), &DimacsOptions::new()) {
+            Err(DimacsError::Variable { line: 1, variable }) => assert_eq!(variable, isize::MAX as usize + 1),
+            other => panic!("{:?}", other),
+        }
+    }
+}
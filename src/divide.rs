@@ -0,0 +1,200 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::monomial::Monomial;
+use super::memoize::Memoize;
+use super::node_hasher::NodeHasherState;
+
+type Memo = Memoize<(NodeIdx, usize), NodeIdx, NodeHasherState>;
+
+fn divide_vars(f: &mut Forest,
+               memo: &mut Memo,
+               idx: NodeIdx,
+               vars: &[Variable],
+               pos: usize) -> NodeIdx
+{
+    if pos == vars.len() { return idx }
+    if idx < 2 { return 0 }
+    if let Some(result) = memo.get(&(idx, pos)) { return result }
+
+    let Node(var, hi, lo) = f.to_node(idx);
+    let result = if var > vars[pos] {
+            0
+        } else if var == vars[pos] {
+            divide_vars(f, memo, hi, vars, pos + 1)
+        } else {
+            let hi = divide_vars(f, memo, hi, vars, pos);
+            let lo = divide_vars(f, memo, lo, vars, pos);
+            f.to_node_idx(Node(var, hi, lo))
+        };
+
+    memo.set((idx, pos), result)
+}
+
+fn remainder_vars(f: &mut Forest,
+                  memo: &mut Memo,
+                  idx: NodeIdx,
+                  vars: &[Variable],
+                  pos: usize) -> NodeIdx
+{
+    if pos == vars.len() { return 0 }
+    if idx < 2 { return idx }
+    if let Some(result) = memo.get(&(idx, pos)) { return result }
+
+    let Node(var, hi, lo) = f.to_node(idx);
+    let result = if var > vars[pos] {
+            idx
+        } else if var == vars[pos] {
+            let hi = remainder_vars(f, memo, hi, vars, pos + 1);
+            f.to_node_idx(Node(var, hi, lo))
+        } else {
+            let hi = remainder_vars(f, memo, hi, vars, pos);
+            let lo = remainder_vars(f, memo, lo, vars, pos);
+            f.to_node_idx(Node(var, hi, lo))
+        };
+
+    memo.set((idx, pos), result)
+}
+
+fn multiply_vars(f: &mut Forest,
+                 memo: &mut Memo,
+                 idx: NodeIdx,
+                 vars: &[Variable],
+                 pos: usize) -> NodeIdx
+{
+    if pos == vars.len() { return idx }
+    if idx == 0 { return 0 }
+    if idx == 1 {
+        return vars[pos..].iter().rev().fold(1, |idx, &var| f.to_node_idx(Node(var, idx, 0)));
+    }
+    if let Some(result) = memo.get(&(idx, pos)) { return result }
+
+    let Node(var, hi, lo) = f.to_node(idx);
+    let result = if vars[pos] < var {
+            let hi = multiply_vars(f, memo, idx, vars, pos + 1);
+            f.to_node_idx(Node(vars[pos], hi, 0))
+        } else if vars[pos] == var {
+            let hi = multiply_vars(f, memo, hi, vars, pos + 1);
+            let lo = multiply_vars(f, memo, lo, vars, pos + 1);
+            let hi = add(f, hi, lo);
+            f.to_node_idx(Node(var, hi, 0))
+        } else {
+            let hi = multiply_vars(f, memo, hi, vars, pos);
+            let lo = multiply_vars(f, memo, lo, vars, pos);
+            f.to_node_idx(Node(var, hi, lo))
+        };
+
+    memo.set((idx, pos), result)
+}
+
+/// The quotient of `idx` by `m`: the sum of `t / m` over the terms `t`
+/// divisible by `m`. This is the coefficient (superpoly) of `m`, and is free
+/// of the variables in `m`.
+pub fn divide(f: &mut Forest, idx: NodeIdx, m: &Monomial) -> NodeIdx {
+    divide_vars(f, &mut Memoize::new(), idx, m.variables(), 0)
+}
+
+/// The terms of `idx` not divisible by `m`, so that
+/// `idx = m * divide(idx, m) + remainder(idx, m)`.
+pub fn remainder(f: &mut Forest, idx: NodeIdx, m: &Monomial) -> NodeIdx {
+    remainder_vars(f, &mut Memoize::new(), idx, m.variables(), 0)
+}
+
+/// `m * idx` in the Boolean ring, respecting the forest's sparsity.
+pub fn multiply_by_monomial(f: &mut Forest, idx: NodeIdx, m: &Monomial) -> NodeIdx {
+    multiply_vars(f, &mut Memoize::new(), idx, m.variables(), 0)
+}
+
+/// The cofactor of `var`: `idx = var * subset1 + subset0`.
+pub fn subset1(f: &mut Forest, idx: NodeIdx, var: Variable) -> NodeIdx {
+    divide_vars(f, &mut Memoize::new(), idx, &[var], 0)
+}
+
+/// The terms of `idx` that do not contain `var`.
+pub fn subset0(f: &mut Forest, idx: NodeIdx, var: Variable) -> NodeIdx {
+    remainder_vars(f, &mut Memoize::new(), idx, &[var], 0)
+}
+
+/// Replaces `var` by `poly` throughout `idx`.
+pub fn substitute(f: &mut Forest, idx: NodeIdx, var: Variable, poly: NodeIdx) -> NodeIdx {
+    let hi = subset1(f, idx, var);
+    let lo = subset0(f, idx, var);
+    let hi = multiply(f, hi, poly);
+    add(f, hi, lo)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::monomial::Monomial;
+
+    #[test]
+    fn divide_basic() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i * 30, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let x0x1x2 = multiply(f, x0x1, x[2]);
+        let x1x3 = multiply(f, x[1], x[3]);
+        let p = add(f, x0x1x2, x1x3);
+        let p = add(f, p, x0x1);
+        let p = add(f, p, x[2]);
+        let p = add(f, p, 1);
+
+        let m = Monomial::from_vars(&[0, 30]);
+        let q = divide(f, p, &m);
+        let r = remainder(f, p, &m);
+
+        let x2_1 = add(f, x[2], 1);
+        assert_eq!(q, x2_1);
+
+        let mq = multiply(f, x0x1, q);
+        assert_eq!(add(f, mq, r), p);
+        assert_eq!(multiply_by_monomial(f, q, &m), mq);
+
+        assert_eq!(subset1(f, p, 90), x[1]);
+        let p0 = subset0(f, p, 90);
+        assert_eq!(add(f, p0, x1x3), p);
+        assert_eq!(divide(f, p, &Monomial::one()), p);
+        assert_eq!(remainder(f, p, &Monomial::one()), 0);
+    }
+
+    #[test]
+    fn divide_substitute() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..3).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let p = add(f, x0x1, x[1]);
+
+        // x1 * (x0 + 1) with x1 := x2 + 1
+        let x2_1 = add(f, x[2], 1);
+        let x0_1 = add(f, x[0], 1);
+        let expected = multiply(f, x2_1, x0_1);
+        assert_eq!(substitute(f, p, 1, x2_1), expected);
+        assert_eq!(substitute(f, p, 2, 0), p);
+        assert_eq!(substitute(f, p, 0, 1), 0);
+    }
+
+    #[test]
+    fn divide_multiply_by_monomial() {
+        let f = &mut Forest::with_sparsity(3);
+
+        let x: Vec<NodeIdx> = (0..5).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let p = add(f, x[0], x[2]);
+        let x1x3 = multiply(f, x[1], x[3]);
+        let p = add(f, p, x1x3);
+        let p = add(f, p, 1);
+
+        for vars in &[vec![0], vec![1, 3], vec![2, 4], vec![0, 1, 2, 3]] {
+            let m = Monomial::from_vars(vars);
+            let m_idx = m.to_node_idx(f);
+            assert_eq!(multiply_by_monomial(f, p, &m), multiply(f, p, m_idx));
+        }
+    }
+}
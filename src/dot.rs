@@ -0,0 +1,193 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::anf::VariableNames;
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone)]
+pub struct DotOptions {
+    /// Only draw nodes at most this many edges below a root.
+    pub max_depth: Option<usize>,
+    /// Draw at most this many nodes, nearest the roots first.
+    pub max_nodes: Option<usize>,
+    pub names: VariableNames,
+}
+
+impl DotOptions {
+    pub fn new() -> DotOptions {
+        DotOptions {
+            max_depth: None,
+            max_nodes: None,
+            names: VariableNames::new(),
+        }
+    }
+}
+
+fn id(idx: NodeIdx) -> String {
+    match idx {
+        0 => "zero".to_string(),
+        1 => "one".to_string(),
+        idx => format!("n{:x}", idx),
+    }
+}
+
+/// A name as the inside of a quoted DOT string.
+fn label(name: &str) -> String {
+    let mut label = String::new();
+    for c in name.chars() {
+        match c {
+            '"' | '\\' => { label.push('\\'); label.push(c) }
+            '\n' => label.push_str("\\n"),
+            c => label.push(c),
+        }
+    }
+    label
+}
+
+/// The graph statements without the surrounding `digraph { }`, for
+/// embedding. See `write_dot`.
+pub fn write_dot_body<W: Write>(f: &Forest,
+                                writer: &mut W,
+                                roots: &[(&str, NodeIdx)],
+                                options: &DotOptions) -> io::Result<()> {
+    // Breadth first, so that truncation keeps the nodes nearest the roots.
+    let mut drawn: HashSet<NodeIdx> = HashSet::new();
+    let mut order: Vec<NodeIdx> = Vec::new();
+    let mut queue: VecDeque<(NodeIdx, usize)> = roots.iter().map(|&(_, idx)| (idx, 0)).collect();
+    while let Some((idx, depth)) = queue.pop_front() {
+        if idx < 2 || drawn.contains(&idx) { continue }
+        if options.max_depth.map_or(false, |max| depth > max) { continue }
+        if options.max_nodes.map_or(false, |max| order.len() >= max) { break }
+
+        drawn.insert(idx);
+        order.push(idx);
+        let Node(_, hi, lo) = f.to_node(idx);
+        queue.push_back((hi, depth + 1));
+        queue.push_back((lo, depth + 1));
+    }
+
+    let mut ranks: BTreeMap<Variable, Vec<NodeIdx>> = BTreeMap::new();
+    let mut edges: Vec<(NodeIdx, NodeIdx, bool)> = Vec::new();
+    for &idx in &order {
+        let Node(var, hi, lo) = f.to_node(idx);
+        ranks.entry(var).or_insert(Vec::new()).push(idx);
+        edges.push((idx, hi, true));
+        edges.push((idx, lo, false));
+    }
+
+    let targets: Vec<NodeIdx> = roots.iter().map(|&(_, idx)| idx)
+        .chain(edges.iter().map(|&(_, to, _)| to))
+        .collect();
+    if targets.contains(&0) { try!(writeln!(writer, "zero [label=\"0\", shape=box];")) }
+    if targets.contains(&1) { try!(writeln!(writer, "one [label=\"1\", shape=box];")) }
+    let truncated = targets.iter().any(|&idx| idx > 1 && !drawn.contains(&idx));
+    if truncated { try!(writeln!(writer, "more [label=\"...\", shape=plaintext];")) }
+
+    for (var, nodes) in &ranks {
+        let name = label(&options.names.name(*var));
+        let ids: Vec<String> = nodes.iter().map(|&idx| id(idx)).collect();
+        for node in &ids {
+            try!(writeln!(writer, "{} [label=\"{}\"];", node, name));
+        }
+        try!(writeln!(writer, "{{ rank=same; {}; }}", ids.join("; ")));
+    }
+
+    let target = |idx: NodeIdx| if idx > 1 && !drawn.contains(&idx) { "more".to_string() } else { id(idx) };
+    for (i, &(name, idx)) in roots.iter().enumerate() {
+        try!(writeln!(writer, "root{} [label=\"{}\", shape=plaintext];", i, label(name)));
+        try!(writeln!(writer, "root{} -> {};", i, target(idx)));
+    }
+    for &(from, to, high) in &edges {
+        let style = if high { "" } else { " [style=dotted]" };
+        try!(writeln!(writer, "{} -> {}{};", id(from), target(to), style));
+    }
+    Ok(())
+}
+
+/// Writes the polynomials at `roots` as one DOT graph, with each root an
+/// entry point labelled by its name. Every node of the forest is drawn
+/// once, however many polynomials share it, and nodes on the same variable
+/// share a rank. High edges are solid, low edges dotted.
+pub fn write_dot<W: Write>(f: &Forest,
+                           writer: &mut W,
+                           roots: &[(&str, NodeIdx)],
+                           options: &DotOptions) -> io::Result<()> {
+    try!(writeln!(writer, "digraph {{"));
+    try!(write_dot_body(f, writer, roots, options));
+    writeln!(writer, "}}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    fn dot(f: &Forest, roots: &[(&str, NodeIdx)], options: &DotOptions) -> String {
+        let mut out = Vec::new();
+        write_dot(f, &mut out, roots, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn dot_sharing() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..70).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+
+        // q = x0*(x69 + 1) + p shares all of p, and has two distinct nodes
+        // on x69.
+        let x1x69 = multiply(f, x[1], x[69]);
+        let p = add(f, x1x69, x[2]);
+        let x69_1 = add(f, x[69], 1);
+        let q = multiply(f, x[0], x69_1);
+        let q = add(f, q, p);
+
+        let out = dot(f, &[("p", p), ("q", q)], &DotOptions::new());
+        assert!(out.starts_with("digraph {\n") && out.ends_with("}\n"));
+        let labels = |name: &str| out.lines().filter(|line| line.ends_with(&format!("[label=\"{}\"];", name))).count();
+        assert_eq!(labels("x0"), 1);
+        assert_eq!(labels("x1"), 1);
+        assert_eq!(labels("x2"), 1);
+        assert_eq!(labels("x69"), 2);
+        assert!(out.contains("root0 [label=\"p\", shape=plaintext];"));
+
+        // Each drawn node has its two edges, and each edge appears once.
+        let nodes = out.lines().filter(|line| line.starts_with('n') && line.contains("[label=")).count();
+        let mut edges: Vec<&str> = out.lines().filter(|line| line.starts_with('n') && line.contains("->")).collect();
+        assert_eq!(edges.len(), 2 * nodes);
+        edges.sort();
+        edges.dedup();
+        assert_eq!(edges.len(), 2 * nodes);
+        assert!(!out.contains("more"));
+    }
+
+    #[test]
+    fn dot_truncation() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..70).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let p = x[66..].iter().fold(0, |p, &x| add(f, p, x));
+
+        let mut options = DotOptions::new();
+        options.max_nodes = Some(2);
+        let out = dot(f, &[("p", p)], &options);
+        assert_eq!(out.lines().filter(|line| line.contains("[label=\"x")).count(), 2);
+        assert!(out.contains(" -> more [style=dotted];"));
+
+        let mut options = DotOptions::new();
+        options.max_depth = Some(0);
+        options.names.insert(66, "k");
+        let out = dot(f, &[("p", p), ("zero", 0)], &options);
+        assert!(out.contains("[label=\"k\"];"));
+        assert!(!out.contains("[label=\"x67\"];"));
+        assert!(out.contains("root1 -> zero;"));
+
+        // Quotes and backslashes in names are escaped.
+        options.names.insert(66, "k\"0\\");
+        let out = dot(f, &[("p \"q\"", p)], &options);
+        assert!(out.contains("[label=\"k\\\"0\\\\\"];"));
+        assert!(out.contains("root0 [label=\"p \\\"q\\\"\", shape=plaintext];"));
+    }
+}
@@ -0,0 +1,156 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::ordering::{DegLex, MonomialOrdering};
+use super::divide::substitute;
+use super::gf2::{to_matrix, from_matrix};
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct ElimLinOutput {
+    /// A basis of the span of the simplified system, in reduced echelon form
+    /// and free of linear equations. `[1]` if the system has no solution.
+    pub system: Vec<NodeIdx>,
+    /// Each eliminated variable with the affine expression it equals, in
+    /// terms of variables that were not eliminated.
+    pub eliminated: Vec<(Variable, NodeIdx)>,
+    /// How many rounds of elimination ran.
+    pub rounds: usize,
+}
+
+impl ElimLinOutput {
+    pub fn is_inconsistent(&self) -> bool {
+        self.system == [1]
+    }
+
+    /// Extends a solution of the simplified system, given as the set of true
+    /// variables, to a solution of the original one.
+    pub fn lift(&self, f: &Forest, solution: &HashSet<Variable>) -> HashSet<Variable> {
+        let mut lifted = solution.clone();
+        for &(var, expr) in &self.eliminated {
+            if f.evaluate(expr, solution) {
+                lifted.insert(var);
+            } else {
+                lifted.remove(&var);
+            }
+        }
+        lifted
+    }
+}
+
+/// ElimLin: eliminates the linear span of the system, substitutes each linear
+/// equation found back into every polynomial, and repeats until no more
+/// appear.
+pub fn elimlin(f: &mut Forest, polys: &[NodeIdx]) -> ElimLinOutput {
+    let mut output = ElimLinOutput {
+        system: polys.iter().cloned().filter(|&p| p != 0).collect(),
+        eliminated: Vec::new(),
+        rounds: 0,
+    };
+
+    loop {
+        output.rounds += 1;
+
+        // The graded ordering puts the linear equations last, with pivots
+        // that no other row contains.
+        let (mut matrix, columns) = to_matrix(f, &output.system, &DegLex);
+        let rank = matrix.echelonize(true).len();
+        let rows: Vec<NodeIdx> = from_matrix(f, &matrix, &columns).into_iter().take(rank).collect();
+
+        if rows.contains(&1) {
+            output.system = vec![1];
+            return output;
+        }
+
+        let (linear, system): (Vec<NodeIdx>, Vec<NodeIdx>) = rows.into_iter()
+            .partition(|&row| f.degree(row) == 1);
+        output.system = system;
+        if linear.is_empty() {
+            return output;
+        }
+
+        for row in linear {
+            let var = DegLex.leading_monomial(f, row).unwrap().variables()[0];
+            let lead = f.to_node_idx(Node(var, 1, 0));
+            let expr = add(f, row, lead);
+
+            for p in output.system.iter_mut() {
+                *p = substitute(f, *p, var, expr);
+            }
+            for entry in output.eliminated.iter_mut() {
+                entry.1 = substitute(f, entry.1, var, expr);
+            }
+            output.eliminated.push((var, expr));
+        }
+        output.system.retain(|&p| p != 0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::monomial::support;
+
+    use std::collections::HashSet;
+
+    fn assignment(mask: u32) -> HashSet<Variable> {
+        (0..8).filter(|&i| mask >> i & 1 == 1).collect()
+    }
+
+    #[test]
+    fn elimlin_lifts_solutions() {
+        let mut lfsr: u32 = 11;
+
+        for _ in 0..10 {
+            let f = &mut Forest::new();
+            let mut polys: Vec<NodeIdx> = Vec::new();
+            for i in 0..7 {
+                let mut p = 0;
+                for _ in 0..4 {
+                    lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let a = f.to_node_idx(Node((lfsr >> 16) as Variable % 8, 1, 0));
+                    let b = f.to_node_idx(Node((lfsr >> 24) as Variable % 8, 1, 0));
+                    // A few of the equations are linear.
+                    let term = if i < 2 { a } else { multiply(f, a, b) };
+                    p = add(f, p, term);
+                }
+                if lfsr & 0x100 != 0 { p = add(f, p, 1) }
+                polys.push(p);
+            }
+
+            let output = elimlin(f, &polys);
+            let eliminated: HashSet<Variable> = output.eliminated.iter().map(|&(var, _)| var).collect();
+            assert_eq!(eliminated.len(), output.eliminated.len());
+            for &(_, expr) in &output.eliminated {
+                assert!(f.degree(expr) <= 1);
+                assert!(support(f, expr).all(|var| !eliminated.contains(&var)));
+            }
+            for &p in &output.system {
+                assert!(output.is_inconsistent() || f.degree(p) > 1);
+                assert!(support(f, p).all(|var| !eliminated.contains(&var)));
+            }
+
+            let mut expected = Vec::new();
+            let mut lifted = Vec::new();
+            for mask in 0..256 {
+                let solution = assignment(mask);
+                if polys.iter().all(|&p| !f.evaluate(p, &solution)) {
+                    expected.push(mask);
+                }
+                if eliminated.iter().any(|var| solution.contains(var)) { continue }
+                if output.system.iter().all(|&p| !f.evaluate(p, &solution)) {
+                    let solution = output.lift(f, &solution);
+                    lifted.push((0..8).filter(|i| solution.contains(i)).fold(0, |m, i| m | 1 << i));
+                }
+            }
+            lifted.sort();
+            assert_eq!(lifted, expected);
+            assert!(!output.is_inconsistent() || expected.is_empty());
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial::{monomials, support};
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// Equations beyond this many are only checked for candidates that satisfy
+/// the first ones.
+const TABLES: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExhaustiveError {
+    /// Only systems of degree at most three are supported.
+    Degree(usize),
+    /// At most 64 variables are supported.
+    Variables(usize),
+}
+
+impl fmt::Display for ExhaustiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExhaustiveError::Degree(degree) => write!(f, "degree {} is above 3", degree),
+            ExhaustiveError::Variables(count) => write!(f, "{} variables are above 64", count),
+        }
+    }
+}
+
+impl Error for ExhaustiveError {
+    fn description(&self) -> &str {
+        match *self {
+            ExhaustiveError::Degree(_) => "system degree too high for exhaustive search",
+            ExhaustiveError::Variables(_) => "too many variables for exhaustive search",
+        }
+    }
+}
+
+fn gray(t: u64) -> u64 {
+    t ^ (t >> 1)
+}
+
+/// A polynomial as words indexed by monomials in the enumerated variables.
+/// Bit `l` of a word is the coefficient's value on lane `l`.
+fn evaluate(terms: &[(u64, u64)], x: u64) -> u64 {
+    terms.iter()
+         .filter(|&&(mask, _)| mask & !x == 0)
+         .fold(0, |value, &(_, word)| value ^ word)
+}
+
+/// The derivative in the directions `s`, ascending, as the enumeration
+/// needs it at step `t`, the first step whose lowest set bits are `s`. The
+/// step before has flipped `s[0]`, and each higher order is the change of
+/// the one below since its previous use.
+fn derivative(terms: &[(u64, u64)], s: &[usize], t: u64) -> u64 {
+    match s.len() {
+        1 => evaluate(terms, gray(t)) ^ evaluate(terms, gray(t - 1)),
+        m => {
+            let prev = t - (1 << (s[m - 2] + 1));
+            derivative(terms, &s[..m - 1], t) ^ derivative(terms, &s[..m - 1], prev)
+        }
+    }
+}
+
+/// The value and derivatives of one polynomial along the Gray code. Third
+/// derivatives of a cubic are constant, as are second ones of a quadratic.
+struct Tables {
+    value: u64,
+    d1: Vec<u64>,
+    d2: Vec<u64>,
+    d3: Vec<u64>,
+}
+
+impl Tables {
+    fn new(terms: &[(u64, u64)], k: usize, degree: usize) -> Tables {
+        let mut tables = Tables {
+            value: evaluate(terms, 0),
+            d1: vec![0; k],
+            d2: vec![0; if degree >= 2 { k * k } else { 0 }],
+            d3: vec![0; if degree >= 3 { k * k * k } else { 0 }],
+        };
+
+        for a in 0..k {
+            tables.d1[a] = derivative(terms, &[a], 1 << a);
+            if degree < 2 { continue }
+
+            for b in a + 1..k {
+                tables.d2[a * k + b] = derivative(terms, &[a, b], 1 << a | 1 << b);
+                if degree < 3 { continue }
+
+                for c in b + 1..k {
+                    let t = 1 << a | 1 << b | 1 << c;
+                    tables.d3[(a * k + b) * k + c] = derivative(terms, &[a, b, c], t);
+                }
+            }
+        }
+
+        tables
+    }
+}
+
+/// Finds the common zeros of polynomials of degree at most three by trying
+/// every assignment of their variables, as in Bouillaguet et al.'s fast
+/// exhaustive search: the assignments are enumerated in Gray code order,
+/// updating each polynomial through tables of its derivatives with a few
+/// XORs per step. The last six variables are bitsliced, so every step
+/// covers 64 assignments at once.
+///
+/// Solutions are the sets of true variables. With `stop_at_first` at most
+/// one is returned.
+pub fn exhaustive_search(f: &Forest,
+                         polys: &[NodeIdx],
+                         stop_at_first: bool) -> Result<Vec<HashSet<Variable>>, ExhaustiveError> {
+    let degree = polys.iter().map(|&p| f.degree(p)).max().unwrap_or(0);
+    if degree > 3 {
+        return Err(ExhaustiveError::Degree(degree));
+    }
+
+    let mut vars: Vec<Variable> = Vec::new();
+    for &p in polys {
+        vars.extend(support(f, p));
+    }
+    vars.sort();
+    vars.dedup();
+    if vars.len() > 64 {
+        return Err(ExhaustiveError::Variables(vars.len()));
+    }
+
+    // Variables `k..` pick the lane, the rest are enumerated.
+    let lanes = vars.len().min(6);
+    let k = vars.len() - lanes;
+    let valid: u64 = if lanes == 6 { !0 } else { (1 << (1 << lanes)) - 1 };
+    let lane_words: Vec<u64> = (0..64u64)
+        .map(|m| (0..64u64).filter(|&l| l & m == m).fold(0, |word, l| word | 1 << l))
+        .collect();
+    let index: HashMap<Variable, usize> = vars.iter().enumerate().map(|(i, &var)| (var, i)).collect();
+
+    let terms: Vec<Vec<(u64, u64)>> = polys.iter().map(|&p| {
+        let mut words: HashMap<u64, u64> = HashMap::new();
+        for m in monomials(f, p) {
+            let (mut outer, mut lane) = (0u64, 0usize);
+            for var in m.variables() {
+                let i = index[var];
+                if i < k { outer |= 1 << i } else { lane |= 1 << (i - k) }
+            }
+            *words.entry(outer).or_insert(0) ^= lane_words[lane];
+        }
+        words.into_iter().filter(|&(_, word)| word != 0).collect()
+    }).collect();
+
+    let mut tables: Vec<Tables> = terms.iter()
+        .take(TABLES)
+        .map(|terms| Tables::new(terms, k, degree))
+        .collect();
+    let rest = &polys[tables.len()..];
+
+    let mut solutions = Vec::new();
+    let mut t: u64 = 0;
+    loop {
+        let zero = !tables.iter().fold(0, |nonzero, table| nonzero | table.value) & valid;
+        if zero != 0 {
+            let x = gray(t);
+            for lane in (0..64u64).filter(|&l| zero >> l & 1 == 1) {
+                let solution: HashSet<Variable> = vars.iter()
+                    .enumerate()
+                    .filter(|&(i, _)| if i < k { x >> i & 1 == 1 } else { lane >> (i - k) & 1 == 1 })
+                    .map(|(_, &var)| var)
+                    .collect();
+                if rest.iter().all(|&p| !f.evaluate(p, &solution)) {
+                    solutions.push(solution);
+                    if stop_at_first {
+                        return Ok(solutions);
+                    }
+                }
+            }
+        }
+
+        t += 1;
+        if t >> k != 0 { break }
+
+        // Step `t` flips variable `a`. Its derivative is brought up to date
+        // by the derivatives along the next set bits of `t`, top down.
+        let a = t.trailing_zeros() as usize;
+        let t1 = t & (t - 1);
+        let b = if t1 != 0 { Some(t1.trailing_zeros() as usize) } else { None };
+        let t2 = t1 & t1.wrapping_sub(1);
+        let c = if t2 != 0 && degree >= 3 { Some(t2.trailing_zeros() as usize) } else { None };
+
+        for table in tables.iter_mut() {
+            if let Some(b) = b {
+                if degree >= 2 {
+                    if let Some(c) = c {
+                        table.d2[a * k + b] ^= table.d3[(a * k + b) * k + c];
+                    }
+                    table.d1[a] ^= table.d2[a * k + b];
+                }
+            }
+            table.value ^= table.d1[a];
+        }
+    }
+
+    Ok(solutions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    use std::collections::HashSet;
+
+    fn random_system(f: &mut Forest, lfsr: &mut u32, n: u32, count: usize, degree: usize) -> Vec<NodeIdx> {
+        (0..count).map(|_| {
+            let mut p = 0;
+            for _ in 0..n + 2 {
+                let mut term = 1;
+                for _ in 0..degree {
+                    *lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let x = f.to_node_idx(Node(((*lfsr >> 16) % n) as Variable, 1, 0));
+                    term = multiply(f, term, x);
+                }
+                p = add(f, p, term);
+            }
+            *lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+            if *lfsr & 0x10000 != 0 { add(f, p, 1) } else { p }
+        }).collect()
+    }
+
+    fn brute_force(f: &Forest, polys: &[NodeIdx], n: u32) -> Vec<Vec<Variable>> {
+        let mut vars: Vec<Variable> = Vec::new();
+        for &p in polys {
+            vars.extend(support(f, p));
+        }
+        vars.sort();
+        vars.dedup();
+
+        (0..1u32 << vars.len())
+            .map(|mask| {
+                vars.iter()
+                    .enumerate()
+                    .filter(|&(i, _)| mask >> i & 1 == 1)
+                    .map(|(_, &var)| var)
+                    .collect::<Vec<Variable>>()
+            })
+            .filter(|solution| {
+                let set: HashSet<Variable> = solution.iter().cloned().collect();
+                assert!(solution.iter().all(|&var| (var as u32) < n));
+                polys.iter().all(|&p| !f.evaluate(p, &set))
+            })
+            .collect()
+    }
+
+    fn sorted(solutions: Vec<HashSet<Variable>>) -> Vec<Vec<Variable>> {
+        let mut solutions: Vec<Vec<Variable>> = solutions.into_iter().map(|solution| {
+            let mut solution: Vec<Variable> = solution.into_iter().collect();
+            solution.sort();
+            solution
+        }).collect();
+        solutions.sort();
+        solutions
+    }
+
+    #[test]
+    fn exhaustive_matches_brute_force() {
+        let mut lfsr = 9;
+
+        for &(n, count, degree) in &[(3, 1, 2), (5, 2, 1), (9, 3, 2), (12, 4, 3), (13, 40, 2), (12, 6, 3)] {
+            let f = &mut Forest::new();
+            let polys = random_system(f, &mut lfsr, n, count, degree);
+
+            let mut expected = brute_force(f, &polys, n);
+            expected.sort();
+            let solutions = exhaustive_search(f, &polys, false).unwrap();
+            assert_eq!(sorted(solutions), expected);
+
+            let first = exhaustive_search(f, &polys, true).unwrap();
+            assert_eq!(first.len(), expected.len().min(1));
+            assert!(sorted(first).iter().all(|solution| expected.contains(solution)));
+        }
+    }
+
+    #[test]
+    fn exhaustive_edge_cases() {
+        let f = &mut Forest::new();
+
+        assert_eq!(exhaustive_search(f, &[], false).unwrap(), vec![HashSet::new()]);
+        assert_eq!(exhaustive_search(f, &[1], false).unwrap(), vec![]);
+
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let x2x3 = multiply(f, x[2], x[3]);
+        let quartic = multiply(f, x0x1, x2x3);
+        assert_eq!(exhaustive_search(f, &[quartic], false), Err(ExhaustiveError::Degree(4)));
+    }
+}
@@ -0,0 +1,153 @@
+use super::node::NodeIdx;
+use super::forest::Forest;
+use super::monomial::{Monomial, monomials};
+use super::ordering::MonomialOrdering;
+use super::normal_form::{Reduction, reduce};
+use super::divide::multiply_by_monomial;
+use super::groebner::{Basis, GroebnerOptions, GroebnerProgress, interreduce};
+use super::gf2::{to_matrix, from_matrix};
+
+use std::collections::HashSet;
+
+/// Adds to `rows` a multiple of a basis element for every monomial that is
+/// divisible by a leading monomial but not yet the leading monomial of a
+/// row, so that the matrix can reduce it away.
+fn preprocess<O>(f: &mut Forest, basis: &Basis, rows: &mut Vec<NodeIdx>, ordering: &O)
+    where O: MonomialOrdering
+{
+    let mut done: HashSet<Monomial> = rows.iter()
+        .filter_map(|&row| ordering.leading_monomial(f, row))
+        .collect();
+    let mut todo: Vec<Monomial> = rows.iter()
+        .flat_map(|&row| monomials(f, row).collect::<Vec<_>>())
+        .collect();
+
+    while let Some(m) = todo.pop() {
+        if !done.insert(m.clone()) { continue }
+
+        let reducer = basis.leads().iter().find(|&&(ref lead, _)| lead.divides(&m));
+        if let Some(&(ref lead, g)) = reducer {
+            let row = multiply_by_monomial(f, g, &m.without(lead));
+            todo.extend(monomials(f, row));
+            rows.push(row);
+        }
+    }
+}
+
+/// Computes a reduced Groebner basis of `polys` with an F4-style algorithm:
+/// all pairs of the lowest degree are reduced together as one GF(2) matrix
+/// with a column per monomial. `progress` is called once per degree step.
+pub fn f4<O, P>(f: &mut Forest,
+                polys: &[NodeIdx],
+                ordering: &O,
+                options: &GroebnerOptions,
+                mut progress: P) -> Vec<NodeIdx>
+    where O: MonomialOrdering,
+          P: FnMut(&GroebnerProgress)
+{
+    let mut basis = Basis::new();
+    let mut zero_reductions = 0;
+
+    for &p in polys {
+        let p = reduce(f, p, basis.leads(), ordering, Reduction::Full);
+        if let Some(lead) = ordering.leading_monomial(f, p) {
+            basis.insert(p, lead);
+        }
+    }
+
+    while !basis.is_inconsistent() {
+        let degree = match basis.min_degree() {
+            Some(degree) => degree,
+            None => break,
+        };
+
+        if options.degree_bound.map_or(false, |bound| degree > bound) {
+            break;
+        }
+
+        let mut rows = Vec::new();
+        for pair in basis.select(degree, options) {
+            let (lhs, rhs) = basis.halves(f, pair);
+            rows.push(lhs);
+            rows.push(rhs);
+        }
+        rows.sort();
+        rows.dedup();
+        rows.retain(|&row| row != 0);
+        if rows.is_empty() { continue }
+
+        preprocess(f, &basis, &mut rows, ordering);
+
+        let (mut matrix, columns) = to_matrix(f, &rows, ordering);
+        let rank = matrix.echelonize(true).len();
+        zero_reductions += rows.len() - rank;
+
+        // Rows led by a basis lead, such as the reducers, add nothing new.
+        // Multiplying by a monomial can cancel the leading term in the
+        // Boolean ring, so the matrix need not have reduced the other rows
+        // by the basis. Finish each one off before it is inserted.
+        let leads: HashSet<Monomial> = basis.leads().iter().map(|&(ref lead, _)| lead.clone()).collect();
+        for g in from_matrix(f, &matrix, &columns).into_iter().take(rank) {
+            match ordering.leading_monomial(f, g) {
+                Some(ref lead) if leads.contains(lead) => continue,
+                _ => {}
+            }
+            let g = reduce(f, g, basis.leads(), ordering, Reduction::Full);
+            match ordering.leading_monomial(f, g) {
+                Some(lead) => basis.insert(g, lead),
+                None => zero_reductions += 1,
+            }
+        }
+
+        progress(&GroebnerProgress {
+            basis: basis.leads().len(),
+            pairs: basis.pairs(),
+            degree: degree,
+            zero_reductions: zero_reductions,
+        });
+    }
+
+    let polys: Vec<NodeIdx> = basis.leads().iter().map(|&(_, g)| g).collect();
+    interreduce(f, &polys, ordering)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::ordering::{Lex, DegRevLex};
+    use super::super::groebner::{GroebnerOptions, groebner_basis};
+
+    #[test]
+    fn f4_matches_buchberger() {
+        let mut lfsr: u32 = 7;
+
+        for _ in 0..16 {
+            let f = &mut Forest::new();
+            let polys: Vec<NodeIdx> = (0..6).map(|_| {
+                let mut p = 0;
+                for _ in 0..5 {
+                    lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let a = f.to_node_idx(Node((lfsr >> 16) as Variable % 7, 1, 0));
+                    let b = f.to_node_idx(Node((lfsr >> 24) as Variable % 7, 1, 0));
+                    let ab = multiply(f, a, b);
+                    p = add(f, p, ab);
+                }
+                if lfsr & 0x100 != 0 { p = add(f, p, 1) }
+                p
+            }).collect();
+
+            let options = GroebnerOptions::new();
+            let expected = groebner_basis(f, &polys, &Lex);
+            assert_eq!(f4(f, &polys, &Lex, &options, |_| {}), expected);
+
+            let expected = groebner_basis(f, &polys, &DegRevLex);
+            let mut steps = 0;
+            assert_eq!(f4(f, &polys, &DegRevLex, &options, |_| steps += 1), expected);
+            assert!(steps > 0 || expected == vec![1]);
+        }
+    }
+}
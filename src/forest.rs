@@ -1,5 +1,6 @@
 use super::node::*;
 use super::node_page::NodePage;
+use super::dot::{DotOptions, write_dot_body};
 
 use std::fmt::{Debug, Formatter, Error};
 use std::cmp::max;
@@ -8,6 +9,13 @@ use std::io::{self, Write};
 
 const HIGH_BIT: NodeIdx = 0x8000_0000_0000_0000;
 
+/// A state of a `Forest` to roll back to. Node indices from before it stay
+/// valid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    nodes: usize,
+}
+
 pub struct Forest {
     nodes: Vec<Node>,
     degrees: Vec<usize>,
@@ -20,34 +28,9 @@ impl Forest {
         Forest::with_sparsity(255)
     }
 
+    /// Writes the graph of `idx` as DOT statements, see `write_dot_body`.
     pub fn write_graph<W: Write>(&self, writer: &mut W, idx: NodeIdx) -> io::Result<()> {
-        if idx < 2 { return Ok(()); }
-
-        let Node(var, hi, lo) = self.to_node(idx);
-
-
-        match hi {
-            1 => try!(writeln!(writer, "{} -> T", var)),
-            0 => try!(writeln!(writer, "{} -> F", var)),
-            x => {
-                let Node(y, _, _) = self.to_node(x);
-                try!(writeln!(writer, "{} -> {}", var, y));
-            }
-        }
-
-        match lo {
-            1 => try!(writeln!(writer, "{} -> T[style=\"dotted\"]", var)),
-            0 => try!(writeln!(writer, "{} -> F[style=\"dotted\"]", var)),
-            x => {
-                let Node(y, _, _) = self.to_node(x);
-                try!(writeln!(writer, "{} -> {}", var, y));
-            }
-        }
-    
-        try!(self.write_graph(writer, hi));
-        try!(self.write_graph(writer, lo));
-
-        return Ok(());
+        write_dot_body(self, writer, &[("root", idx)], &DotOptions::new())
     }
 
     pub fn with_sparsity(sparsity: usize) -> Forest {
@@ -157,6 +140,28 @@ impl Forest {
     pub fn sparsity(&self) -> usize {
         self.sparsity
     }
+
+    /// The number of nodes stored, not counting those packed into their
+    /// index.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint { nodes: self.nodes.len() }
+    }
+
+    /// Drops every node created since `checkpoint`. Their indices must no
+    /// longer be used.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        if checkpoint.nodes >= self.nodes.len() { return }
+
+        self.nodes.truncate(checkpoint.nodes);
+        self.degrees.truncate(checkpoint.nodes);
+        for page in self.node_pages.iter_mut() {
+            page.truncate(checkpoint.nodes);
+        }
+    }
 }
 
 impl Debug for Forest {
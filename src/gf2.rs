@@ -0,0 +1,612 @@
+use super::node::NodeIdx;
+use super::forest::Forest;
+use super::add::add;
+use super::monomial::{Monomial, monomials};
+use super::ordering::MonomialOrdering;
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// How many pivots the dense elimination gathers before building a table of
+/// their combinations.
+const BLOCK: usize = 8;
+
+/// A dense matrix over GF(2), packed 64 columns to a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitMatrix {
+    rows: usize,
+    cols: usize,
+    words: usize,
+    data: Vec<u64>,
+}
+
+impl BitMatrix {
+    pub fn new(rows: usize, cols: usize) -> BitMatrix {
+        let words = (cols + 63) / 64;
+        BitMatrix {
+            rows: rows,
+            cols: cols,
+            words: words,
+            data: vec![0; rows * words],
+        }
+    }
+
+    pub fn identity(n: usize) -> BitMatrix {
+        let mut matrix = BitMatrix::new(n, n);
+        for i in 0..n {
+            matrix.set(i, i, true);
+        }
+        matrix
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.data[row * self.words + col / 64] >> (col % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: bool) {
+        let word = &mut self.data[row * self.words + col / 64];
+        if value {
+            *word |= 1 << (col % 64);
+        } else {
+            *word &= !(1 << (col % 64));
+        }
+    }
+
+    /// The packed words of `row`; bits past `cols` are always zero.
+    pub fn row(&self, row: usize) -> &[u64] {
+        &self.data[row * self.words..(row + 1) * self.words]
+    }
+
+    /// Appends a zero row and returns its index.
+    pub fn push_row(&mut self) -> usize {
+        self.data.extend((0..self.words).map(|_| 0));
+        self.rows += 1;
+        self.rows - 1
+    }
+
+    pub fn swap_rows(&mut self, a: usize, b: usize) {
+        if a == b { return }
+        for w in 0..self.words {
+            self.data.swap(a * self.words + w, b * self.words + w);
+        }
+    }
+
+    /// Adds row `src` to row `dst`.
+    pub fn xor_row(&mut self, dst: usize, src: usize) {
+        for w in 0..self.words {
+            let word = self.data[src * self.words + w];
+            self.data[dst * self.words + w] ^= word;
+        }
+    }
+
+    /// The first non-zero column of `row`.
+    pub fn pivot(&self, row: usize) -> Option<usize> {
+        self.row(row)
+            .iter()
+            .position(|&word| word != 0)
+            .map(|w| w * 64 + self.row(row)[w].trailing_zeros() as usize)
+    }
+
+    pub fn transpose(&self) -> BitMatrix {
+        let mut matrix = BitMatrix::new(self.cols, self.rows);
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.get(row, col) {
+                    matrix.set(col, row, true);
+                }
+            }
+        }
+        matrix
+    }
+
+    /// `self * x` for a column vector `x`.
+    pub fn mul_vec(&self, x: &[bool]) -> Vec<bool> {
+        (0..self.rows)
+            .map(|row| (0..self.cols).filter(|&col| x[col] && self.get(row, col)).count() % 2 == 1)
+            .collect()
+    }
+
+    fn reduce_by_block(&mut self, row: usize, start: usize, block: &[usize]) {
+        for (i, &col) in block.iter().enumerate() {
+            if self.get(row, col) {
+                self.xor_row(row, start + i);
+            }
+        }
+    }
+
+    /// Brings the matrix into row echelon form, or reduced row echelon form
+    /// if `reduced` is set, and returns the pivot columns. Row `i` has its
+    /// pivot in the `i`th returned column and the remaining rows are zero.
+    ///
+    /// This is Gaussian elimination with the method of the four Russians:
+    /// pivots are found `BLOCK` at a time, and every other row is then
+    /// reduced by all of them with a single lookup into a table holding the
+    /// sums of every subset of the pivot rows.
+    pub fn echelonize(&mut self, reduced: bool) -> Vec<usize> {
+        let mut pivots: Vec<usize> = Vec::new();
+        let mut col = 0;
+
+        while col < self.cols && pivots.len() < self.rows {
+            let start = pivots.len();
+
+            // Rows are reduced by the pivots of the current block only when
+            // they are inspected, which keeps the search cheap.
+            while pivots.len() - start < BLOCK && col < self.cols && pivots.len() < self.rows {
+                let rank = pivots.len();
+                let mut found = None;
+                for row in rank..self.rows {
+                    self.reduce_by_block(row, start, &pivots[start..]);
+                    if self.get(row, col) {
+                        found = Some(row);
+                        break;
+                    }
+                }
+
+                if let Some(row) = found {
+                    self.swap_rows(row, rank);
+                    for i in start..rank {
+                        if self.get(i, col) {
+                            self.xor_row(i, rank);
+                        }
+                    }
+                    pivots.push(col);
+                }
+                col += 1;
+            }
+
+            let rank = pivots.len();
+            if rank == start { break }
+
+            // The pivot rows vanish before their first pivot, so the table
+            // only needs the words from there on.
+            let block = &pivots[start..];
+            let offset = block[0] / 64;
+            let width = self.words - offset;
+            let mut table = vec![0; width << block.len()];
+            for i in 1..(1 << block.len()) {
+                let low = (i as u64).trailing_zeros() as usize;
+                let prev = i & (i - 1);
+                let src = (start + low) * self.words + offset;
+                for w in 0..width {
+                    table[i * width + w] = table[prev * width + w] ^ self.data[src + w];
+                }
+            }
+
+            let first = if reduced { 0 } else { rank };
+            for row in (first..start).chain(rank..self.rows) {
+                let i = block.iter()
+                             .enumerate()
+                             .fold(0, |i, (bit, &col)| i | (self.get(row, col) as usize) << bit);
+                if i == 0 { continue }
+
+                let dst = row * self.words + offset;
+                for w in 0..width {
+                    self.data[dst + w] ^= table[i * width + w];
+                }
+            }
+        }
+
+        pivots
+    }
+
+    pub fn echelon_form(&self) -> BitMatrix {
+        let mut matrix = self.clone();
+        matrix.echelonize(false);
+        matrix
+    }
+
+    pub fn reduced_echelon_form(&self) -> BitMatrix {
+        let mut matrix = self.clone();
+        matrix.echelonize(true);
+        matrix
+    }
+
+    pub fn rank(&self) -> usize {
+        self.clone().echelonize(false).len()
+    }
+
+    /// A basis of the right kernel `{ x : self * x = 0 }`, one vector per
+    /// row.
+    pub fn kernel(&self) -> BitMatrix {
+        let mut matrix = self.clone();
+        let pivots = matrix.echelonize(true);
+        let free: Vec<usize> = (0..self.cols)
+            .filter(|col| pivots.binary_search(col).is_err())
+            .collect();
+
+        let mut kernel = BitMatrix::new(free.len(), self.cols);
+        for (k, &col) in free.iter().enumerate() {
+            kernel.set(k, col, true);
+            for (i, &pivot) in pivots.iter().enumerate() {
+                if matrix.get(i, col) {
+                    kernel.set(k, pivot, true);
+                }
+            }
+        }
+        kernel
+    }
+
+    /// Some `x` with `self * x = rhs`, taking every free variable as zero,
+    /// or `None` if the system is inconsistent.
+    pub fn solve(&self, rhs: &[bool]) -> Option<Vec<bool>> {
+        let mut augmented = BitMatrix::new(self.rows, self.cols + 1);
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.get(row, col) {
+                    augmented.set(row, col, true);
+                }
+            }
+            augmented.set(row, self.cols, rhs[row]);
+        }
+
+        let pivots = augmented.echelonize(true);
+        if pivots.last() == Some(&self.cols) {
+            return None;
+        }
+
+        let mut x = vec![false; self.cols];
+        for (i, &pivot) in pivots.iter().enumerate() {
+            x[pivot] = augmented.get(i, self.cols);
+        }
+        Some(x)
+    }
+}
+
+/// A sparse matrix over GF(2): each row is the sorted list of its non-zero
+/// columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseMatrix {
+    cols: usize,
+    rows: Vec<Vec<usize>>,
+}
+
+/// The sum of two sorted rows.
+fn xor_sorted(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut sum = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            sum.push(a[i]);
+            i += 1;
+        } else if b[j] < a[i] {
+            sum.push(b[j]);
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+    sum.extend(a[i..].iter().cloned());
+    sum.extend(b[j..].iter().cloned());
+
+    sum
+}
+
+impl SparseMatrix {
+    pub fn new(cols: usize) -> SparseMatrix {
+        SparseMatrix { cols: cols, rows: Vec::new() }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn row(&self, row: usize) -> &[usize] {
+        &self.rows[row]
+    }
+
+    /// Appends the sum of the unit vectors in `cols`, so a column listed
+    /// twice cancels.
+    pub fn push_row(&mut self, cols: &[usize]) -> usize {
+        let mut cols = cols.to_vec();
+        cols.sort();
+
+        let mut row: Vec<usize> = Vec::with_capacity(cols.len());
+        for col in cols {
+            assert!(col < self.cols);
+            if row.last() == Some(&col) {
+                row.pop();
+            } else {
+                row.push(col);
+            }
+        }
+
+        self.rows.push(row);
+        self.rows.len() - 1
+    }
+
+    pub fn from_dense(matrix: &BitMatrix) -> SparseMatrix {
+        SparseMatrix {
+            cols: matrix.cols(),
+            rows: (0..matrix.rows())
+                      .map(|row| (0..matrix.cols()).filter(|&col| matrix.get(row, col)).collect())
+                      .collect(),
+        }
+    }
+
+    pub fn to_dense(&self) -> BitMatrix {
+        let mut matrix = BitMatrix::new(self.rows.len(), self.cols);
+        for (i, row) in self.rows.iter().enumerate() {
+            for &col in row {
+                matrix.set(i, col, true);
+            }
+        }
+        matrix
+    }
+
+    /// Structured elimination: rows are bucketed by their first column and
+    /// each column is cleared with the lightest row leading there, which
+    /// keeps fill-in low on sparse systems. Zero rows are dropped; otherwise
+    /// this matches `BitMatrix::echelonize`.
+    pub fn echelonize(&mut self, reduced: bool) -> Vec<usize> {
+        let mut buckets: Vec<Vec<Vec<usize>>> = (0..self.cols).map(|_| Vec::new()).collect();
+        for row in mem::replace(&mut self.rows, Vec::new()) {
+            if let Some(&col) = row.first() {
+                buckets[col].push(row);
+            }
+        }
+
+        let mut pivots = Vec::new();
+        for col in 0..self.cols {
+            let mut bucket = mem::replace(&mut buckets[col], Vec::new());
+            if bucket.is_empty() { continue }
+
+            let lightest = (0..bucket.len()).min_by_key(|&i| bucket[i].len()).unwrap();
+            let pivot = bucket.swap_remove(lightest);
+            for row in bucket {
+                let row = xor_sorted(&row, &pivot);
+                if let Some(&first) = row.first() {
+                    buckets[first].push(row);
+                }
+            }
+
+            pivots.push(col);
+            self.rows.push(pivot);
+        }
+
+        if reduced {
+            for i in (0..self.rows.len()).rev() {
+                let col = pivots[i];
+                for j in 0..i {
+                    if self.rows[j].binary_search(&col).is_ok() {
+                        let row = xor_sorted(&self.rows[j], &self.rows[i]);
+                        self.rows[j] = row;
+                    }
+                }
+            }
+        }
+
+        pivots
+    }
+
+    pub fn rank(&self) -> usize {
+        self.clone().echelonize(false).len()
+    }
+}
+
+/// The monomials of `polys`, largest first under `ordering`, with their
+/// column indices.
+fn columns<O>(f: &Forest, polys: &[NodeIdx], ordering: &O) -> (Vec<Monomial>, HashMap<Monomial, usize>)
+    where O: MonomialOrdering
+{
+    let mut columns: Vec<Monomial> = polys.iter()
+        .flat_map(|&p| monomials(f, p).collect::<Vec<_>>())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    columns.sort_by(|a, b| ordering.compare(b, a));
+
+    let index = columns.iter().cloned().enumerate().map(|(i, m)| (m, i)).collect();
+    (columns, index)
+}
+
+/// The coefficient matrix of `polys` with one row per polynomial and one
+/// column per monomial, largest first under `ordering`. Echelon forms of
+/// the matrix thus have distinct leading monomials in their rows.
+pub fn to_matrix<O>(f: &Forest, polys: &[NodeIdx], ordering: &O) -> (BitMatrix, Vec<Monomial>)
+    where O: MonomialOrdering
+{
+    let (columns, index) = columns(f, polys, ordering);
+
+    let mut matrix = BitMatrix::new(polys.len(), columns.len());
+    for (row, &p) in polys.iter().enumerate() {
+        for m in monomials(f, p) {
+            matrix.set(row, index[&m], true);
+        }
+    }
+
+    (matrix, columns)
+}
+
+/// Like `to_matrix`, for systems too large to be stored densely.
+pub fn to_sparse_matrix<O>(f: &Forest,
+                           polys: &[NodeIdx],
+                           ordering: &O) -> (SparseMatrix, Vec<Monomial>)
+    where O: MonomialOrdering
+{
+    let (columns, index) = columns(f, polys, ordering);
+
+    let mut matrix = SparseMatrix::new(columns.len());
+    for &p in polys {
+        let row: Vec<usize> = monomials(f, p).map(|m| index[&m]).collect();
+        matrix.push_row(&row);
+    }
+
+    (matrix, columns)
+}
+
+fn to_polynomial<I>(f: &mut Forest, cols: I, columns: &[Monomial]) -> NodeIdx
+    where I: Iterator<Item = usize>
+{
+    let mut p = 0;
+    for col in cols {
+        let m = columns[col].to_node_idx(f);
+        p = add(f, p, m);
+    }
+    p
+}
+
+/// The polynomial of every row, including zero rows.
+pub fn from_matrix(f: &mut Forest, matrix: &BitMatrix, columns: &[Monomial]) -> Vec<NodeIdx> {
+    (0..matrix.rows())
+        .map(|row| {
+            let cols = (0..matrix.cols()).filter(|&col| matrix.get(row, col));
+            to_polynomial(f, cols, columns)
+        })
+        .collect()
+}
+
+pub fn from_sparse_matrix(f: &mut Forest,
+                          matrix: &SparseMatrix,
+                          columns: &[Monomial]) -> Vec<NodeIdx> {
+    (0..matrix.rows())
+        .map(|row| to_polynomial(f, matrix.row(row).iter().cloned(), columns))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::ordering::{Lex, DegRevLex};
+
+    fn random_matrix(lfsr: &mut u32, rows: usize, cols: usize, density: u32) -> BitMatrix {
+        let mut matrix = BitMatrix::new(rows, cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                *lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                if (*lfsr >> 16) % 100 < density {
+                    matrix.set(row, col, true);
+                }
+            }
+        }
+        matrix
+    }
+
+    #[test]
+    fn gf2_echelon() {
+        let mut lfsr = 1;
+
+        for &(rows, cols, density) in &[(5, 5, 50), (40, 70, 50), (90, 30, 10), (130, 150, 3)] {
+            let a = random_matrix(&mut lfsr, rows, cols, density);
+
+            let mut reduced = a.clone();
+            let pivots = reduced.echelonize(true);
+            for (i, &pivot) in pivots.iter().enumerate() {
+                assert_eq!(reduced.pivot(i), Some(pivot));
+                for j in 0..rows {
+                    assert_eq!(reduced.get(j, pivot), i == j);
+                }
+            }
+            for i in pivots.len()..rows {
+                assert_eq!(reduced.pivot(i), None);
+            }
+
+            let echelon = a.echelon_form();
+            assert_eq!(echelon.reduced_echelon_form(), reduced);
+            for i in 1..pivots.len() {
+                assert!(echelon.pivot(i) > echelon.pivot(i - 1));
+            }
+
+            // The row space is unchanged, and both modes agree.
+            let mut stacked = reduced.clone();
+            for row in 0..rows {
+                let dst = stacked.push_row();
+                for col in 0..cols {
+                    stacked.set(dst, col, a.get(row, col));
+                }
+            }
+            assert_eq!(stacked.rank(), pivots.len());
+
+            let mut sparse = SparseMatrix::from_dense(&a);
+            assert_eq!(sparse.echelonize(true), pivots);
+            assert_eq!(sparse.rank(), pivots.len());
+            let mut truncated = sparse.to_dense();
+            while truncated.rows() < rows {
+                truncated.push_row();
+            }
+            assert_eq!(truncated, reduced);
+
+            let kernel = a.kernel();
+            assert_eq!(kernel.rows(), cols - pivots.len());
+            assert_eq!(kernel.rank(), kernel.rows());
+            for k in 0..kernel.rows() {
+                let x: Vec<bool> = (0..cols).map(|col| kernel.get(k, col)).collect();
+                assert!(a.mul_vec(&x).iter().all(|&bit| !bit));
+            }
+        }
+    }
+
+    #[test]
+    fn gf2_solve() {
+        let mut lfsr = 3;
+
+        for &(rows, cols) in &[(10, 10), (30, 20), (20, 30)] {
+            let a = random_matrix(&mut lfsr, rows, cols, 30);
+            let x: Vec<bool> = (0..cols).map(|col| col % 3 == 0).collect();
+            let b = a.mul_vec(&x);
+
+            let y = a.solve(&b).unwrap();
+            assert_eq!(a.mul_vec(&y), b);
+        }
+
+        let mut a = BitMatrix::identity(3);
+        a.set(2, 1, true);
+        let b = a.clone();
+        let row = a.push_row();
+        a.xor_row(row, 1);
+        a.xor_row(row, 2);
+        assert_eq!(b.solve(&[true, false, true]), Some(vec![true, false, true]));
+        assert_eq!(a.solve(&[true, true, false, true]), Some(vec![true, true, true]));
+        assert_eq!(a.solve(&[true, true, false, false]), None);
+        assert_eq!(a.transpose().transpose(), a);
+    }
+
+    #[test]
+    fn gf2_polynomials() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let x2x3 = multiply(f, x[2], x[3]);
+        let p = add(f, x0x1, x[2]);
+        let q = add(f, x0x1, x2x3);
+        let q = add(f, q, 1);
+        let r = add(f, x[2], x2x3);
+        let r = add(f, r, 1);
+        let polys = vec![p, q, r];
+
+        let (mut matrix, columns) = to_matrix(f, &polys, &DegRevLex);
+        assert_eq!(columns.len(), 4);
+        assert_eq!(columns[0], Monomial::from_vars(&[0, 1]));
+        assert_eq!(from_matrix(f, &matrix, &columns), polys);
+
+        // r = p + q, leaving the two rows x0*x1 + x2 and x2*x3 + x2 + 1.
+        assert_eq!(matrix.echelonize(true).len(), 2);
+        let reduced = from_matrix(f, &matrix, &columns);
+        assert_eq!(reduced, vec![p, r, 0]);
+
+        let (mut sparse, columns) = to_sparse_matrix(f, &polys, &Lex);
+        sparse.echelonize(true);
+        let reduced = from_sparse_matrix(f, &sparse, &columns);
+        let (mut dense, _) = to_matrix(f, &polys, &Lex);
+        dense.echelonize(true);
+        assert_eq!(reduced[..], from_matrix(f, &dense, &columns)[..2]);
+    }
+}
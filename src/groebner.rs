@@ -0,0 +1,426 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::monomial::Monomial;
+use super::ordering::MonomialOrdering;
+use super::normal_form::{Reduction, reduce, with_leads};
+use super::divide::multiply_by_monomial;
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct GroebnerOptions {
+    /// Skip pairs whose least common multiple exceeds this degree. The
+    /// result is then only a partial basis.
+    pub degree_bound: Option<usize>,
+    /// Skip pairs with coprime leading monomials.
+    pub product_criterion: bool,
+    /// Buchberger's chain criterion.
+    pub chain_criterion: bool,
+}
+
+impl GroebnerOptions {
+    pub fn new() -> GroebnerOptions {
+        GroebnerOptions {
+            degree_bound: None,
+            product_criterion: true,
+            chain_criterion: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroebnerProgress {
+    pub basis: usize,
+    pub pairs: usize,
+    pub degree: usize,
+    pub zero_reductions: usize,
+}
+
+/// A critical pair. `Variable(i, x)` stands for the pair of basis element
+/// `i` with the field equation `x^2 + x`, which has to be considered for
+/// every `x` in the leading monomial.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pair {
+    Critical(usize, usize),
+    Variable(usize, Variable),
+}
+
+/// A growing basis with its pending critical pairs. Elements whose leading
+/// monomial is a multiple of a later one's are kept for reduction but form
+/// no further pairs.
+pub struct Basis {
+    leads: Vec<(Monomial, NodeIdx)>,
+    redundant: Vec<bool>,
+    pairs: Vec<Pair>,
+    treated: HashSet<(usize, usize)>,
+}
+
+impl Basis {
+    pub fn new() -> Basis {
+        Basis {
+            leads: Vec::new(),
+            redundant: Vec::new(),
+            pairs: Vec::new(),
+            treated: HashSet::new(),
+        }
+    }
+
+    pub fn leads(&self) -> &[(Monomial, NodeIdx)] {
+        &self.leads
+    }
+
+    pub fn pairs(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_inconsistent(&self) -> bool {
+        self.leads.iter().any(|&(_, g)| g == 1)
+    }
+
+    /// Adds a non-zero polynomial, already reduced by the basis, and queues
+    /// its pairs with the existing elements.
+    pub fn insert(&mut self, g: NodeIdx, lead: Monomial) {
+        let i = self.leads.len();
+
+        for &var in lead.variables() {
+            self.pairs.push(Pair::Variable(i, var));
+        }
+        for j in 0..i {
+            if self.redundant[j] { continue }
+
+            self.pairs.push(Pair::Critical(j, i));
+            if lead.divides(&self.leads[j].0) {
+                self.redundant[j] = true;
+            }
+        }
+
+        self.leads.push((lead, g));
+        self.redundant.push(false);
+    }
+
+    pub fn degree(&self, pair: Pair) -> usize {
+        match pair {
+            Pair::Critical(i, j) => self.leads[i].0.lcm(&self.leads[j].0).degree(),
+            Pair::Variable(i, _) => self.leads[i].0.degree(),
+        }
+    }
+
+    pub fn min_degree(&self) -> Option<usize> {
+        self.pairs.iter().map(|&pair| self.degree(pair)).min()
+    }
+
+    /// Removes the pending pairs of the given degree from the queue and
+    /// returns those that survive the criteria. Pairs are checked one at a
+    /// time so that a pair is only ever dropped in favour of pairs that
+    /// were kept or treated before it.
+    pub fn select(&mut self, degree: usize, options: &GroebnerOptions) -> Vec<Pair> {
+        let (taken, rest): (Vec<Pair>, Vec<Pair>) = self.pairs
+            .iter()
+            .partition(|&&pair| self.degree(pair) == degree);
+        self.pairs = rest;
+
+        let mut selected = Vec::with_capacity(taken.len());
+        for pair in taken {
+            let useless = self.is_useless(pair, options);
+            if let Pair::Critical(i, j) = pair {
+                self.treated.insert((i, j));
+            }
+            if !useless {
+                selected.push(pair);
+            }
+        }
+
+        selected
+    }
+
+    fn is_useless(&self, pair: Pair, options: &GroebnerOptions) -> bool {
+        let (i, j) = match pair {
+            Pair::Critical(i, j) => (i, j),
+            Pair::Variable(..) => return false,
+        };
+        let (ref lead_i, _) = self.leads[i];
+        let (ref lead_j, _) = self.leads[j];
+
+        if options.product_criterion && lead_i.gcd(lead_j).degree() == 0 {
+            return true;
+        }
+
+        if options.chain_criterion {
+            let lcm = lead_i.lcm(lead_j);
+            let key = |a: usize, b: usize| if a < b { (a, b) } else { (b, a) };
+
+            for (k, &(ref lead_k, _)) in self.leads.iter().enumerate() {
+                if k == i || k == j || !lead_k.divides(&lcm) { continue }
+                if self.treated.contains(&key(i, k)) && self.treated.contains(&key(j, k)) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// The two multiples of basis elements whose sum is the S-polynomial.
+    pub fn halves(&self, f: &mut Forest, pair: Pair) -> (NodeIdx, NodeIdx) {
+        match pair {
+            Pair::Critical(i, j) => {
+                let (ref lead_i, g_i) = self.leads[i];
+                let (ref lead_j, g_j) = self.leads[j];
+                let lhs = multiply_by_monomial(f, g_i, &lead_j.without(lead_i));
+                let rhs = multiply_by_monomial(f, g_j, &lead_i.without(lead_j));
+                (lhs, rhs)
+            }
+            Pair::Variable(i, var) => {
+                let (_, g) = self.leads[i];
+                (multiply_by_monomial(f, g, &Monomial::from_vars(&[var])), 0)
+            }
+        }
+    }
+
+    pub fn spolynomial(&self, f: &mut Forest, pair: Pair) -> NodeIdx {
+        let (lhs, rhs) = self.halves(f, pair);
+        add(f, lhs, rhs)
+    }
+}
+
+/// Turns a basis into the reduced Groebner basis it generates, assuming it
+/// is a Groebner basis already: drops elements whose leading monomial is a
+/// multiple of another's and fully reduces the rest. The result is sorted
+/// by leading monomial, largest first.
+pub fn interreduce<O>(f: &mut Forest, basis: &[NodeIdx], ordering: &O) -> Vec<NodeIdx>
+    where O: MonomialOrdering
+{
+    let mut leads = with_leads(f, basis, ordering);
+    if leads.iter().any(|&(ref lead, _)| lead.degree() == 0) {
+        return vec![1];
+    }
+
+    leads.sort_by(|a, b| match ordering.compare(&a.0, &b.0) {
+        Ordering::Equal => a.1.cmp(&b.1),
+        ordering => ordering,
+    });
+
+    let mut unique: Vec<(Monomial, NodeIdx)> = Vec::with_capacity(leads.len());
+    for lead in leads {
+        if unique.last().map_or(true, |last| last.0 != lead.0) {
+            unique.push(lead);
+        }
+    }
+
+    let minimal: Vec<(Monomial, NodeIdx)> = unique.iter()
+        .filter(|&&(ref lead, _)| !unique.iter().any(|&(ref other, _)| {
+            other != lead && other.divides(lead)
+        }))
+        .cloned()
+        .collect();
+
+    let mut reduced = Vec::with_capacity(minimal.len());
+    for (i, &(_, g)) in minimal.iter().enumerate().rev() {
+        let others: Vec<(Monomial, NodeIdx)> = minimal.iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, lead)| lead.clone())
+            .collect();
+        reduced.push(reduce(f, g, &others, ordering, Reduction::Full));
+    }
+
+    reduced
+}
+
+/// Computes a reduced Groebner basis of `polys` with Buchberger's
+/// algorithm, calling `progress` after every pair.
+///
+/// Products are truncated at the forest's sparsity, so the result is only
+/// exact when the sparsity does not cut any monomial off.
+pub fn buchberger<O, P>(f: &mut Forest,
+                        polys: &[NodeIdx],
+                        ordering: &O,
+                        options: &GroebnerOptions,
+                        mut progress: P) -> Vec<NodeIdx>
+    where O: MonomialOrdering,
+          P: FnMut(&GroebnerProgress)
+{
+    let mut basis = Basis::new();
+    let mut zero_reductions = 0;
+
+    for &p in polys {
+        let p = reduce(f, p, basis.leads(), ordering, Reduction::Full);
+        if let Some(lead) = ordering.leading_monomial(f, p) {
+            basis.insert(p, lead);
+        }
+    }
+
+    while !basis.is_inconsistent() {
+        let degree = match basis.min_degree() {
+            Some(degree) => degree,
+            None => break,
+        };
+
+        if options.degree_bound.map_or(false, |bound| degree > bound) {
+            break;
+        }
+
+        for pair in basis.select(degree, options) {
+            let s = basis.spolynomial(f, pair);
+            let s = reduce(f, s, basis.leads(), ordering, Reduction::Full);
+
+            match ordering.leading_monomial(f, s) {
+                Some(lead) => basis.insert(s, lead),
+                None => zero_reductions += 1,
+            }
+
+            progress(&GroebnerProgress {
+                basis: basis.leads().len(),
+                pairs: basis.pairs(),
+                degree: degree,
+                zero_reductions: zero_reductions,
+            });
+
+            if basis.is_inconsistent() { break }
+        }
+    }
+
+    let polys: Vec<NodeIdx> = basis.leads().iter().map(|&(_, g)| g).collect();
+    interreduce(f, &polys, ordering)
+}
+
+/// `buchberger` with default options and no progress reporting.
+pub fn groebner_basis<O>(f: &mut Forest, polys: &[NodeIdx], ordering: &O) -> Vec<NodeIdx>
+    where O: MonomialOrdering
+{
+    buchberger(f, polys, ordering, &GroebnerOptions::new(), |_| {})
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::ordering::{MonomialOrdering, Lex, DegLex, DegRevLex};
+    use super::super::normal_form::{Reduction, normal_form};
+
+    use std::collections::HashSet;
+
+    fn solutions(f: &Forest, polys: &[NodeIdx], vars: usize) -> Vec<HashSet<Variable>> {
+        (0..1usize << vars).map(|bits| {
+                (0..vars).filter(|&i| bits >> i & 1 == 1).map(|i| i as Variable).collect()
+            })
+            .filter(|set| polys.iter().all(|&p| !f.evaluate(p, set)))
+            .collect()
+    }
+
+    fn system(f: &mut Forest, seed: u32, vars: usize, count: usize) -> Vec<NodeIdx> {
+        let mut lfsr = seed;
+        let mut next = move || { lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345); lfsr >> 16 };
+
+        (0..count).map(|_| {
+            let mut p = 0;
+            for _ in 0..4 {
+                let a = f.to_node_idx(Node((next() as usize % vars) as Variable, 1, 0));
+                let b = f.to_node_idx(Node((next() as usize % vars) as Variable, 1, 0));
+                let ab = multiply(f, a, b);
+                p = add(f, p, ab);
+            }
+            if next() & 1 == 1 { p = add(f, p, 1); }
+            p
+        }).collect()
+    }
+
+    #[test]
+    fn groebner_basic() {
+        let f = &mut Forest::new();
+
+        let x0 = f.to_node_idx(Node(0, 1, 0));
+        let x1 = f.to_node_idx(Node(1, 1, 0));
+        let x0x1 = multiply(f, x0, x1);
+        let g = add(f, x0x1, 1);
+
+        // x0*x1 = 1 forces both variables to 1.
+        let x0_1 = add(f, x0, 1);
+        let x1_1 = add(f, x1, 1);
+        assert_eq!(groebner_basis(f, &[g], &Lex), vec![x0_1, x1_1]);
+
+        let h = add(f, x0, x1);
+        assert_eq!(groebner_basis(f, &[g, h, x0], &DegLex), vec![1]);
+        assert_eq!(groebner_basis(f, &[0], &Lex), vec![]);
+    }
+
+    #[test]
+    fn groebner_solutions() {
+        for seed in 0..8 {
+            let f = &mut Forest::new();
+            let polys = system(f, seed, 6, 5);
+            let expected = solutions(f, &polys, 6);
+
+            let plain = GroebnerOptions {
+                degree_bound: None,
+                product_criterion: false,
+                chain_criterion: false,
+            };
+            let reference = buchberger(f, &polys, &Lex, &plain, |_| {});
+            assert_eq!(groebner_basis(f, &polys, &Lex), reference);
+            assert_eq!(solutions(f, &reference, 6), expected);
+
+            let basis = groebner_basis(f, &polys, &DegRevLex);
+            assert_eq!(solutions(f, &basis, 6), expected);
+            for &p in &polys {
+                assert_eq!(normal_form(f, p, &basis, &DegRevLex, Reduction::Full), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn groebner_progress() {
+        let f = &mut Forest::new();
+        let polys = system(f, 3, 6, 4);
+
+        let mut calls = 0;
+        let mut options = GroebnerOptions::new();
+        options.degree_bound = Some(2);
+        buchberger(f, &polys, &DegLex, &options, |progress| {
+            assert!(progress.degree <= 2);
+            calls += 1;
+        });
+        assert!(calls > 0);
+    }
+
+    #[test]
+    fn groebner_redundant() {
+        let f = &mut Forest::new();
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let p = add(f, x0x1, x[2]);
+        let q = add(f, x[0], x[3]);
+        let r = add(f, x[1], x[3]);
+
+        // x0 divides x0*x1, so p forms no pairs once q is in.
+        let mut basis = Basis::new();
+        for &g in &[p, q, r] {
+            let lead = Lex.leading_monomial(f, g).unwrap();
+            basis.insert(g, lead);
+        }
+        assert_eq!(basis.redundant, vec![true, false, false]);
+        assert!(basis.pairs.contains(&Pair::Critical(1, 2)));
+        assert!(!basis.pairs.contains(&Pair::Critical(0, 2)));
+
+        // With the pairs of p never formed, the chain criterion must not
+        // count them as treated.
+        let plain = GroebnerOptions {
+            degree_bound: None,
+            product_criterion: false,
+            chain_criterion: false,
+        };
+        let reference = buchberger(f, &[p, q, r], &Lex, &plain, |_| {});
+        assert_eq!(groebner_basis(f, &[p, q, r], &Lex), reference);
+        assert_eq!(solutions(f, &reference, 4), solutions(f, &[p, q, r], 4));
+        for seed in 8..24 {
+            let polys = system(f, seed, 5, 6);
+            let reference = buchberger(f, &polys, &DegLex, &plain, |_| {});
+            assert_eq!(groebner_basis(f, &polys, &DegLex), reference);
+        }
+    }
+}
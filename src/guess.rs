@@ -0,0 +1,365 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial::{monomials, support};
+use super::substitution::{Substitution, eliminate_linear};
+use super::exhaustive::exhaustive_search;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// Solves the systems left over once the guessed variables are fixed.
+pub trait InnerSolver {
+    /// All solutions as sets of true variables, or `None` if the system
+    /// could not be decided. Solutions range over the variables the system
+    /// mentions; those it does not are left out.
+    fn solve(&mut self, f: &mut Forest, polys: &[NodeIdx]) -> Option<Vec<HashSet<Variable>>>;
+}
+
+impl<F> InnerSolver for F
+    where F: FnMut(&mut Forest, &[NodeIdx]) -> Option<Vec<HashSet<Variable>>>
+{
+    fn solve(&mut self, f: &mut Forest, polys: &[NodeIdx]) -> Option<Vec<HashSet<Variable>>> {
+        self(f, polys)
+    }
+}
+
+fn variables(f: &Forest, polys: &[NodeIdx]) -> Vec<Variable> {
+    let mut vars: Vec<Variable> = Vec::new();
+    for &p in polys {
+        vars.extend(support(f, p));
+    }
+    vars.sort();
+    vars.dedup();
+    vars
+}
+
+/// Every way of extending `solution` by some of the `free` variables.
+fn expand(solution: HashSet<Variable>, free: &[Variable]) -> Vec<HashSet<Variable>> {
+    let mut solutions = vec![solution];
+    for &var in free {
+        let with: Vec<HashSet<Variable>> = solutions.iter().map(|solution| {
+            let mut solution = solution.clone();
+            solution.insert(var);
+            solution
+        }).collect();
+        solutions.extend(with);
+    }
+    solutions
+}
+
+/// Decides systems that linear elimination reduces to nothing, returning
+/// every point of their affine solution space. The count doubles with each
+/// variable left free by the elimination.
+#[derive(Debug, Copy, Clone)]
+pub struct LinearSolver;
+
+impl InnerSolver for LinearSolver {
+    fn solve(&mut self, f: &mut Forest, polys: &[NodeIdx]) -> Option<Vec<HashSet<Variable>>> {
+        let (system, history) = eliminate_linear(f, polys);
+        if system == [1] {
+            Some(Vec::new())
+        } else if system.is_empty() {
+            let eliminated: HashSet<Variable> = history.steps().iter().map(|&(var, _)| var).collect();
+            let free: Vec<Variable> = variables(f, polys).into_iter()
+                .filter(|var| !eliminated.contains(var))
+                .collect();
+            Some(expand(HashSet::new(), &free).iter().map(|point| history.lift(f, point)).collect())
+        } else {
+            None
+        }
+    }
+}
+
+/// Runs `exhaustive_search` on systems of degree at most three.
+#[derive(Debug, Copy, Clone)]
+pub struct ExhaustiveSolver {
+    pub stop_at_first: bool,
+}
+
+impl InnerSolver for ExhaustiveSolver {
+    fn solve(&mut self, f: &mut Forest, polys: &[NodeIdx]) -> Option<Vec<HashSet<Variable>>> {
+        exhaustive_search(f, polys, self.stop_at_first).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GuessHeuristic {
+    /// The variables in the most monomials.
+    MostFrequent,
+    /// The variables that contribute the most to the degree, counting each
+    /// monomial with its degree.
+    HighestDegree,
+    /// Exactly these variables.
+    Given(Vec<Variable>),
+}
+
+#[derive(Debug, Clone)]
+pub struct GuessOptions {
+    /// How many variables to guess.
+    pub count: usize,
+    pub heuristic: GuessHeuristic,
+    /// Stop after the first branch with a solution.
+    pub stop_at_first: bool,
+}
+
+impl GuessOptions {
+    pub fn new(count: usize) -> GuessOptions {
+        GuessOptions {
+            count: count,
+            heuristic: GuessHeuristic::MostFrequent,
+            stop_at_first: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuessError {
+    /// At most 63 variables can be guessed, as the branches are counted in
+    /// a `u64`.
+    TooManyGuesses(usize),
+}
+
+impl fmt::Display for GuessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GuessError::TooManyGuesses(count) => write!(f, "{} guesses are above 63", count),
+        }
+    }
+}
+
+impl Error for GuessError {
+    fn description(&self) -> &str {
+        match *self {
+            GuessError::TooManyGuesses(_) => "too many variables to guess",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GuessReport {
+    pub guessed: Vec<Variable>,
+    pub solutions: Vec<HashSet<Variable>>,
+    /// Assignments tried.
+    pub branches: usize,
+    /// Branches that fixing the guesses alone made inconsistent.
+    pub pruned: usize,
+    /// Branches the inner solver could not decide.
+    pub undecided: usize,
+    /// The most nodes any branch added to the forest.
+    pub peak_nodes: usize,
+}
+
+/// The variables `heuristic` would guess first, at most `count` of them.
+pub fn choose_guesses(f: &Forest,
+                      polys: &[NodeIdx],
+                      heuristic: &GuessHeuristic,
+                      count: usize) -> Vec<Variable> {
+    if let GuessHeuristic::Given(ref vars) = *heuristic {
+        return vars.iter().cloned().take(count).collect();
+    }
+
+    let mut scores: HashMap<Variable, usize> = HashMap::new();
+    for &p in polys {
+        for m in monomials(f, p) {
+            let weight = match *heuristic {
+                GuessHeuristic::HighestDegree => m.degree(),
+                _ => 1,
+            };
+            for &var in m.variables() {
+                *scores.entry(var).or_insert(0) += weight;
+            }
+        }
+    }
+
+    let mut vars: Vec<(Variable, usize)> = scores.into_iter().collect();
+    vars.sort_by(|a, b| match b.1.cmp(&a.1) {
+        Ordering::Equal => a.0.cmp(&b.0),
+        ordering => ordering,
+    });
+    vars.into_iter().map(|(var, _)| var).take(count).collect()
+}
+
+/// Guess-and-determine: fixes the chosen variables to each of their
+/// assignments in turn and hands the restricted system to `solver`. The
+/// forest is rolled back after every branch, so only the nodes of one
+/// branch are alive at a time.
+///
+/// Variables that drop out of the system in some branch are free there, and
+/// each solution of that branch is reported with every assignment of them,
+/// so that with a complete inner solver `solutions` is the full set.
+pub fn guess_and_determine<S>(f: &mut Forest,
+                              polys: &[NodeIdx],
+                              options: &GuessOptions,
+                              solver: &mut S) -> Result<GuessReport, GuessError>
+    where S: InnerSolver
+{
+    let guessed = choose_guesses(f, polys, &options.heuristic, options.count);
+    if guessed.len() >= 64 {
+        return Err(GuessError::TooManyGuesses(guessed.len()));
+    }
+    let vars = variables(f, polys);
+    let mut report = GuessReport {
+        guessed: guessed.clone(),
+        solutions: Vec::new(),
+        branches: 0,
+        pruned: 0,
+        undecided: 0,
+        peak_nodes: 0,
+    };
+
+    for assignment in 0..1u64 << guessed.len() {
+        report.branches += 1;
+        let checkpoint = f.checkpoint();
+        let before = f.len();
+
+        let mut substitution = Substitution::new();
+        for (i, &var) in guessed.iter().enumerate() {
+            substitution.insert(var, (assignment >> i & 1) as NodeIdx);
+        }
+        let mut restricted = substitution.apply_all(f, polys);
+        restricted.retain(|&p| p != 0);
+
+        if restricted.contains(&1) {
+            report.pruned += 1;
+        } else {
+            let remaining = variables(f, &restricted);
+            let free: Vec<Variable> = vars.iter().cloned()
+                .filter(|var| !guessed.contains(var) && remaining.binary_search(var).is_err())
+                .collect();
+
+            match solver.solve(f, &restricted) {
+                Some(solutions) => {
+                    for mut solution in solutions {
+                        for (i, &var) in guessed.iter().enumerate() {
+                            if assignment >> i & 1 == 1 {
+                                solution.insert(var);
+                            } else {
+                                solution.remove(&var);
+                            }
+                        }
+                        for var in &free {
+                            solution.remove(var);
+                        }
+                        report.solutions.extend(expand(solution, &free));
+                    }
+                }
+                None => report.undecided += 1,
+            }
+        }
+
+        report.peak_nodes = report.peak_nodes.max(f.len() - before);
+        f.rollback(checkpoint);
+
+        if options.stop_at_first && !report.solutions.is_empty() { break }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    use std::collections::HashSet;
+
+    fn sorted(solutions: &[HashSet<Variable>]) -> Vec<Vec<Variable>> {
+        let mut solutions: Vec<Vec<Variable>> = solutions.iter().map(|solution| {
+            let mut solution: Vec<Variable> = solution.iter().cloned().collect();
+            solution.sort();
+            solution
+        }).collect();
+        solutions.sort();
+        solutions
+    }
+
+    #[test]
+    fn guess_forest_rollback() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..70).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let p = multiply(f, x[1], x[69]);
+        let checkpoint = f.checkpoint();
+        let len = f.len();
+
+        let q = add(f, p, x[68]);
+        let r = multiply(f, q, x[0]);
+        assert!(f.len() > len);
+        f.rollback(checkpoint);
+        assert_eq!(f.len(), len);
+
+        // Rebuilding finds the same nodes again, and old ones still work.
+        let q2 = add(f, p, x[68]);
+        assert_eq!(q2, q);
+        assert_eq!(multiply(f, q2, x[0]), r);
+        assert_eq!(multiply(f, x[1], x[69]), p);
+    }
+
+    #[test]
+    fn guess_solves() {
+        let f = &mut Forest::new();
+        let mut lfsr: u32 = 3;
+        let solution: HashSet<Variable> = [1, 2, 5, 8, 9].iter().cloned().collect();
+
+        // Every quadratic term has a factor below 3, so guessing x0, x1 and
+        // x2 leaves linear equations.
+        let polys: Vec<NodeIdx> = (0..14).map(|_| {
+            let mut p = 0;
+            for _ in 0..4 {
+                lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                let a = f.to_node_idx(Node(((lfsr >> 16) % 3) as Variable, 1, 0));
+                let b = f.to_node_idx(Node(((lfsr >> 24) % 10) as Variable, 1, 0));
+                let ab = multiply(f, a, b);
+                p = add(f, p, ab);
+                p = add(f, p, b);
+            }
+            if f.evaluate(p, &solution) { add(f, p, 1) } else { p }
+        }).collect();
+
+        let mut options = GuessOptions::new(3);
+        options.heuristic = GuessHeuristic::Given(vec![0, 1, 2]);
+        assert_eq!(choose_guesses(f, &polys, &options.heuristic, 2), vec![0, 1]);
+
+        let mut exhaustive = ExhaustiveSolver { stop_at_first: false };
+        let expected = guess_and_determine(f, &polys, &GuessOptions::new(0), &mut exhaustive).unwrap();
+        assert_eq!(expected.branches, 1);
+        assert!(expected.solutions.contains(&solution));
+
+        // Variables that drop out of a branch are enumerated, so every
+        // heuristic finds the full set.
+        let all = sorted(&expected.solutions);
+        for heuristic in vec![GuessHeuristic::MostFrequent, GuessHeuristic::HighestDegree] {
+            options.heuristic = heuristic;
+            let report = guess_and_determine(f, &polys, &options, &mut exhaustive).unwrap();
+            assert_eq!(report.branches, 8);
+            assert_eq!(report.guessed.len(), 3);
+            assert_eq!(sorted(&report.solutions), all);
+        }
+
+        options.heuristic = GuessHeuristic::Given(vec![0, 1, 2]);
+        let report = guess_and_determine(f, &polys, &options, &mut LinearSolver).unwrap();
+        assert_eq!(report.undecided, 0);
+        assert_eq!(sorted(&report.solutions), all);
+
+        let mut calls = 0;
+        let report = {
+            let mut undecided = |_: &mut Forest, _: &[NodeIdx]| -> Option<Vec<HashSet<Variable>>> {
+                calls += 1;
+                None
+            };
+            guess_and_determine(f, &polys, &options, &mut undecided).unwrap()
+        };
+        assert_eq!(report.undecided + report.pruned, 8);
+        assert_eq!(calls, report.undecided);
+
+        options.count = 64;
+        options.heuristic = GuessHeuristic::Given((0..64).collect());
+        assert_eq!(guess_and_determine(f, &polys, &options, &mut LinearSolver).unwrap_err(),
+                   GuessError::TooManyGuesses(64));
+    }
+}
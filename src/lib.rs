@@ -7,10 +7,35 @@ extern crate test;
 
 pub use node::{Node, Variable, NodeIdx};
 pub use node_page::NodePage;
-pub use forest::Forest;
+pub use forest::{Forest, Checkpoint};
 pub use add::add;
 pub use multiply::multiply;
 pub use monomial_count::monomial_count;
+pub use monomial::{Monomial, Monomials, Support, monomials, monomials_of_degree, support};
+pub use ordering::{MonomialOrdering, Lex, DegLex, DegRevLex, Block, Elimination};
+pub use ordering::{leading_monomial, lead_term, tail};
+pub use divide::{divide, remainder, multiply_by_monomial, subset0, subset1, substitute};
+pub use normal_form::{Reduction, normal_form, linear_normal_form};
+pub use groebner::{GroebnerOptions, GroebnerProgress, buchberger, groebner_basis, interreduce};
+pub use f4::f4;
+pub use gf2::{BitMatrix, SparseMatrix, to_matrix, to_sparse_matrix, from_matrix, from_sparse_matrix};
+pub use xl::{XlOptions, XlError, XlOutput, xl};
+pub use elimlin::{ElimLinOutput, elimlin};
+pub use system::{PolynomialSystem, Component, SystemStats, WordConstraints, combine_solutions};
+pub use substitution::{Substitution, SubstitutionHistory, eliminate_linear};
+pub use exhaustive::{ExhaustiveError, exhaustive_search};
+pub use guess::{InnerSolver, LinearSolver, ExhaustiveSolver, GuessHeuristic, GuessOptions, GuessReport};
+pub use guess::{GuessError, choose_guesses, guess_and_determine};
+pub use cnf::{Literal, CnfOptions, CnfVariable, Cnf, DimacsOptions, DimacsError, read_dimacs};
+pub use sat::{SatBackend, SatError, Cdcl, ExternalSolver, parse_solver_output};
+pub use anf::{VariableNames, Anf, ParseAnfError, anf, parse_anf};
+pub use anf::{AnfFileError, AnfReader, read_anf, write_anf};
+pub use script::{ScriptOrder, write_sage, write_magma, parse_basis};
+pub use dot::{DotOptions, write_dot, write_dot_body};
+pub use smt::{SmtOptions, SmtError, SmtScript, write_smt2};
+pub use aiger::{Aiger, AigerError, AigerCircuit, read_aiger};
+pub use bristol::{Gate, BristolCircuit, BristolError, read_bristol};
+pub use netlist::{NetlistOptions, write_verilog, write_blif};
 pub use word::Word;
 pub use node_hasher::NodeHasherState;
 
@@ -20,5 +45,28 @@ mod forest;
 mod add;
 mod multiply;
 mod monomial_count;
+mod monomial;
+mod ordering;
+mod divide;
+mod normal_form;
+mod groebner;
+mod f4;
+mod gf2;
+mod xl;
+mod elimlin;
+mod system;
+mod substitution;
+mod exhaustive;
+mod guess;
+mod cnf;
+mod sat;
+mod anf;
+mod script;
+mod dot;
+mod smt;
+mod aiger;
+mod bristol;
+mod netlist;
+mod memoize;
 mod word;
 mod node_hasher;
@@ -43,11 +43,13 @@ impl<I, O, S> Debug for Memoize<I, O, S>
           S: HashState,
 {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let min_max = self.counts.iter().map(|(_, &count)| count).min_max();
+        let min_max = (self.counts.iter().map(|(_, &count)| count).min(),
+                       self.counts.iter().map(|(_, &count)| count).max());
         let avg = self.counts.iter()
             .map(|(_, &count)| count)
             .fold(0f64, |avg, count| avg + count as f64) / self.counts.len() as f64;
-        writeln!(f, "Memoize {{ length: {}, min/max hits: {:?} }}", self.map.len(), min_max)
+        writeln!(f, "Memoize {{ length: {}, min/max hits: {:?}, average hits: {} }}",
+                 self.map.len(), min_max, avg)
     }
 }
 
@@ -0,0 +1,236 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+
+use std::collections::HashSet;
+use std::vec;
+
+/// A product of distinct variables, kept as a sorted set.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Monomial {
+    vars: Vec<Variable>,
+}
+
+impl Monomial {
+    pub fn one() -> Monomial {
+        Monomial { vars: Vec::new() }
+    }
+
+    pub fn from_vars(vars: &[Variable]) -> Monomial {
+        let mut vars = vars.to_vec();
+        vars.sort();
+        vars.dedup();
+        Monomial { vars: vars }
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        &self.vars
+    }
+
+    pub fn degree(&self) -> usize {
+        self.vars.len()
+    }
+
+    pub fn contains(&self, var: Variable) -> bool {
+        self.vars.binary_search(&var).is_ok()
+    }
+
+    pub fn divides(&self, other: &Monomial) -> bool {
+        let mut rest = other.vars.iter();
+
+        'outer: for &var in &self.vars {
+            while let Some(&x) = rest.next() {
+                if x == var { continue 'outer }
+                if x > var { return false }
+            }
+            return false;
+        }
+
+        true
+    }
+
+    pub fn lcm(&self, other: &Monomial) -> Monomial {
+        let mut vars = Vec::with_capacity(self.vars.len() + other.vars.len());
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.vars.len() && j < other.vars.len() {
+            let (x, y) = (self.vars[i], other.vars[j]);
+            if x <= y { vars.push(x); i += 1; }
+            if y <= x { if y < x { vars.push(y); } j += 1; }
+        }
+        vars.extend(self.vars[i..].iter().cloned());
+        vars.extend(other.vars[j..].iter().cloned());
+
+        Monomial { vars: vars }
+    }
+
+    pub fn gcd(&self, other: &Monomial) -> Monomial {
+        Monomial {
+            vars: self.vars.iter()
+                      .filter(|&&var| other.contains(var))
+                      .cloned()
+                      .collect(),
+        }
+    }
+
+    /// The variables of `self` that are not in `other`; the quotient when
+    /// `other` divides `self`.
+    pub fn without(&self, other: &Monomial) -> Monomial {
+        Monomial {
+            vars: self.vars.iter()
+                      .filter(|&&var| !other.contains(var))
+                      .cloned()
+                      .collect(),
+        }
+    }
+
+    pub fn to_node_idx(&self, f: &mut Forest) -> NodeIdx {
+        self.vars.iter().rev().fold(1, |idx, &var| f.to_node_idx(Node(var, idx, 0)))
+    }
+}
+
+/// Iterates the monomials of a polynomial in ZDD path order, that is
+/// lexicographically descending with `x0 > x1 > ...`.
+pub struct Monomials<'a> {
+    forest: &'a Forest,
+    stack: Vec<(NodeIdx, usize)>,
+    path: Vec<Variable>,
+    degree: Option<usize>,
+}
+
+impl<'a> Iterator for Monomials<'a> {
+    type Item = Monomial;
+
+    fn next(&mut self) -> Option<Monomial> {
+        while let Some((idx, len)) = self.stack.pop() {
+            self.path.truncate(len);
+
+            if idx == 0 { continue }
+            if let Some(degree) = self.degree {
+                if len > degree || len + self.forest.degree(idx) < degree {
+                    continue;
+                }
+            }
+            if idx == 1 {
+                if self.degree.map_or(true, |degree| degree == len) {
+                    return Some(Monomial { vars: self.path.clone() });
+                }
+                continue;
+            }
+
+            let Node(var, hi, lo) = self.forest.to_node(idx);
+            self.stack.push((lo, len));
+            self.stack.push((hi, len + 1));
+            self.path.push(var);
+        }
+
+        None
+    }
+}
+
+pub fn monomials(f: &Forest, idx: NodeIdx) -> Monomials {
+    Monomials {
+        forest: f,
+        stack: vec![(idx, 0)],
+        path: Vec::new(),
+        degree: None,
+    }
+}
+
+/// Like `monomials`, restricted to terms of exactly `degree`. Branches that
+/// cannot reach `degree` are skipped using the forest's stored degrees.
+pub fn monomials_of_degree(f: &Forest, idx: NodeIdx, degree: usize) -> Monomials {
+    Monomials {
+        forest: f,
+        stack: vec![(idx, 0)],
+        path: Vec::new(),
+        degree: Some(degree),
+    }
+}
+
+/// Iterates the variables a polynomial depends on, in ascending order.
+pub struct Support {
+    vars: vec::IntoIter<Variable>,
+}
+
+impl Iterator for Support {
+    type Item = Variable;
+
+    fn next(&mut self) -> Option<Variable> {
+        self.vars.next()
+    }
+}
+
+pub fn support(f: &Forest, idx: NodeIdx) -> Support {
+    let mut seen = HashSet::new();
+    let mut vars = HashSet::new();
+    let mut stack = vec![idx];
+
+    while let Some(idx) = stack.pop() {
+        if idx < 2 || !seen.insert(idx) { continue }
+
+        let Node(var, hi, lo) = f.to_node(idx);
+        vars.insert(var);
+        stack.push(hi);
+        stack.push(lo);
+    }
+
+    let mut vars: Vec<Variable> = vars.into_iter().collect();
+    vars.sort();
+
+    Support { vars: vars.into_iter() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::Node;
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    #[test]
+    fn monomial_basic() {
+        let a = Monomial::from_vars(&[3, 1, 1]);
+        let b = Monomial::from_vars(&[1, 2, 3]);
+
+        assert_eq!(a.variables(), &[1, 3]);
+        assert_eq!(a.degree(), 2);
+        assert!(a.divides(&b));
+        assert!(!b.divides(&a));
+        assert!(Monomial::one().divides(&a));
+        assert_eq!(a.lcm(&Monomial::from_vars(&[0, 3])).variables(), &[0, 1, 3]);
+        assert_eq!(a.gcd(&Monomial::from_vars(&[0, 3])).variables(), &[3]);
+        assert_eq!(b.without(&a).variables(), &[2]);
+    }
+
+    #[test]
+    fn monomial_iter() {
+        let f = &mut Forest::new();
+
+        let x = f.to_node_idx(Node(0, 1, 0));
+        let y = f.to_node_idx(Node(1, 1, 0));
+        let z = f.to_node_idx(Node(70, 1, 0));
+
+        let xy = multiply(f, x, y);
+        let xyz = multiply(f, xy, z);
+        let p = add(f, xyz, y);
+        let p = add(f, p, 1);
+
+        let terms: Vec<Monomial> = monomials(f, p).collect();
+        assert_eq!(terms, vec![Monomial::from_vars(&[0, 1, 70]),
+                               Monomial::from_vars(&[1]),
+                               Monomial::one()]);
+
+        for term in &terms {
+            let m = term.to_node_idx(f);
+            assert_eq!(monomials(f, m).collect::<Vec<_>>(), vec![term.clone()]);
+        }
+
+        let linear: Vec<Monomial> = monomials_of_degree(f, p, 1).collect();
+        assert_eq!(linear, vec![Monomial::from_vars(&[1])]);
+        assert_eq!(monomials_of_degree(f, p, 2).count(), 0);
+
+        assert_eq!(support(f, p).collect::<Vec<_>>(), vec![0, 1, 70]);
+        assert_eq!(support(f, 1).count(), 0);
+    }
+}
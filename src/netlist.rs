@@ -0,0 +1,370 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial::support;
+use super::anf::VariableNames;
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone)]
+pub struct NetlistOptions {
+    /// The module or model name.
+    pub module: String,
+    /// Names of the input ports.
+    pub names: VariableNames,
+}
+
+impl NetlistOptions {
+    pub fn new() -> NetlistOptions {
+        NetlistOptions {
+            module: "boo".to_string(),
+            names: VariableNames::new(),
+        }
+    }
+}
+
+/// The inputs and the gate nodes below `outputs`, children before their
+/// parents. A node that is a bare variable is its input port.
+fn netlist(f: &Forest, outputs: &[(&str, NodeIdx)]) -> (Vec<Variable>, Vec<NodeIdx>) {
+    let mut inputs: Vec<Variable> = Vec::new();
+    for &(_, p) in outputs {
+        inputs.extend(support(f, p));
+    }
+    inputs.sort();
+    inputs.dedup();
+
+    let mut nodes: Vec<NodeIdx> = Vec::new();
+    let mut seen: HashSet<NodeIdx> = HashSet::new();
+    let mut stack: Vec<NodeIdx> = outputs.iter().map(|&(_, idx)| idx).collect();
+    while let Some(idx) = stack.pop() {
+        if idx < 2 || !seen.insert(idx) { continue }
+        let Node(_, hi, lo) = f.to_node(idx);
+        if hi != 1 || lo != 0 { nodes.push(idx) }
+        stack.push(hi);
+        stack.push(lo);
+    }
+    // Children are on larger variables than their parents.
+    nodes.sort_by_key(|&idx| (!f.to_node(idx).0, idx));
+    (inputs, nodes)
+}
+
+/// Whether `name` is of the form `n<hex>` the gate wires take.
+fn is_wire(name: &str) -> bool {
+    name.starts_with('n') && name.len() > 1 && name[1..].chars().all(|c| c.is_digit(16))
+}
+
+/// The identifier for `name`, escaped unless it is simple. An escaped
+/// identifier ends at whitespace and is the same as the simple one, so
+/// names with whitespace, or that would be a gate wire, have none.
+fn verilog_identifier(name: &str) -> Option<String> {
+    let keywords = ["module", "endmodule", "input", "output", "wire", "assign"];
+    let printable = name.chars().all(|c| (c as u32) > 32 && (c as u32) < 127);
+    if name.is_empty() || !printable || is_wire(name) {
+        return None;
+    }
+    let simple = !name.starts_with(|c: char| c.is_digit(10) || c == '$') &&
+                 name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') &&
+                 !keywords.contains(&name);
+    Some(if simple { name.to_string() } else { format!("\\{} ", name) })
+}
+
+fn blif_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_whitespace() || c == '#' || c == '=' { '_' } else { c }).collect()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Checks that the output names are all distinct from each other and from
+/// the inputs.
+fn check_ports(inputs: &[String], outputs: &[String]) -> io::Result<()> {
+    let mut ports: HashSet<&str> = inputs.iter().map(|name| &name[..]).collect();
+    for name in outputs {
+        if !ports.insert(name) {
+            return Err(invalid(format!("port {} is taken", name)));
+        }
+    }
+    Ok(())
+}
+
+/// The wire, port or constant carrying a polynomial.
+fn signal<F>(f: &Forest, idx: NodeIdx, input: &F) -> String
+    where F: Fn(Variable) -> String
+{
+    match idx {
+        0 => "1'b0".to_string(),
+        1 => "1'b1".to_string(),
+        idx => {
+            let Node(var, hi, lo) = f.to_node(idx);
+            if hi == 1 && lo == 0 { input(var) } else { format!("n{:x}", idx) }
+        }
+    }
+}
+
+/// Writes the polynomials as a structural Verilog module, with an input
+/// port per variable and an output port per polynomial. Each node is one
+/// wire `x & hi ^ lo`, so shared nodes are built once.
+///
+/// Inputs whose names cannot be identifiers are called `x<i>`; such output
+/// or module names, and outputs that clash with another port, are an
+/// `InvalidInput` error.
+pub fn write_verilog<W: Write>(f: &Forest,
+                               writer: &mut W,
+                               outputs: &[(&str, NodeIdx)],
+                               options: &NetlistOptions) -> io::Result<()> {
+    let (inputs, nodes) = netlist(f, outputs);
+    let input = |var: Variable| verilog_identifier(&options.names.name(var)).unwrap_or(format!("x{}", var));
+    let inputs: Vec<String> = inputs.into_iter().map(&input).collect();
+    let mut names: Vec<String> = Vec::new();
+    for &(name, _) in outputs {
+        match verilog_identifier(name) {
+            Some(name) => names.push(name),
+            None => return Err(invalid(format!("output {:?} is no identifier", name))),
+        }
+    }
+    try!(check_ports(&inputs, &names));
+    let module = match verilog_identifier(&options.module) {
+        Some(module) => module,
+        None => return Err(invalid(format!("module {:?} is no identifier", options.module))),
+    };
+
+    let ports: Vec<String> = inputs.iter().chain(names.iter()).cloned().collect();
+    try!(writeln!(writer, "module {}({});", module, ports.join(", ")));
+    for name in &inputs {
+        try!(writeln!(writer, "  input {};", name));
+    }
+    for name in &names {
+        try!(writeln!(writer, "  output {};", name));
+    }
+    for &idx in &nodes {
+        try!(writeln!(writer, "  wire n{:x};", idx));
+    }
+
+    for &idx in &nodes {
+        let Node(var, hi, lo) = f.to_node(idx);
+        let x = input(var);
+        let product = if hi == 1 { x } else { format!("{} & {}", x, signal(f, hi, &input)) };
+        let sum = if lo == 0 { product } else { format!("{} ^ {}", product, signal(f, lo, &input)) };
+        try!(writeln!(writer, "  assign n{:x} = {};", idx, sum));
+    }
+    for (name, &(_, idx)) in names.iter().zip(outputs.iter()) {
+        try!(writeln!(writer, "  assign {} = {};", name, signal(f, idx, &input)));
+    }
+    writeln!(writer, "endmodule")
+}
+
+/// Writes the same netlist as a BLIF model, one `.names` table per node,
+/// with whitespace in names replaced by `_`.
+pub fn write_blif<W: Write>(f: &Forest,
+                            writer: &mut W,
+                            outputs: &[(&str, NodeIdx)],
+                            options: &NetlistOptions) -> io::Result<()> {
+    let (inputs, nodes) = netlist(f, outputs);
+    let input = |var: Variable| {
+        let name = blif_name(&options.names.name(var));
+        if name.is_empty() || is_wire(&name) { format!("x{}", var) } else { name }
+    };
+    let inputs: Vec<String> = inputs.into_iter().map(&input).collect();
+    let names: Vec<String> = outputs.iter().map(|&(name, _)| blif_name(name)).collect();
+    if let Some(name) = names.iter().find(|name| name.is_empty() || is_wire(name)) {
+        return Err(invalid(format!("output {:?} cannot be a signal", name)));
+    }
+    try!(check_ports(&inputs, &names));
+
+    try!(writeln!(writer, ".model {}", blif_name(&options.module)));
+    try!(writeln!(writer, ".inputs {}", inputs.join(" ")));
+    try!(writeln!(writer, ".outputs {}", names.join(" ")));
+
+    for &idx in &nodes {
+        let Node(var, hi, lo) = f.to_node(idx);
+        let x = input(var);
+        // Rows of the on-set of x * hi + lo, where hi is never zero and
+        // x * 1 + 0 is the input itself.
+        match (hi, lo) {
+            (1, 1) => {
+                try!(writeln!(writer, ".names {} n{:x}", x, idx));
+                try!(writeln!(writer, "0 1"));
+            }
+            (1, lo) => {
+                try!(writeln!(writer, ".names {} {} n{:x}", x, signal(f, lo, &input), idx));
+                try!(writeln!(writer, "10 1\n01 1"));
+            }
+            (hi, 0) => {
+                try!(writeln!(writer, ".names {} {} n{:x}", x, signal(f, hi, &input), idx));
+                try!(writeln!(writer, "11 1"));
+            }
+            (hi, 1) => {
+                try!(writeln!(writer, ".names {} {} n{:x}", x, signal(f, hi, &input), idx));
+                try!(writeln!(writer, "0- 1\n10 1"));
+            }
+            (hi, lo) => {
+                try!(writeln!(writer, ".names {} {} {} n{:x}", x, signal(f, hi, &input), signal(f, lo, &input), idx));
+                try!(writeln!(writer, "110 1\n0-1 1\n101 1"));
+            }
+        }
+    }
+    for (name, &(_, idx)) in names.iter().zip(outputs.iter()) {
+        match idx {
+            0 => try!(writeln!(writer, ".names {}", name)),
+            1 => try!(writeln!(writer, ".names {}\n1", name)),
+            idx => try!(writeln!(writer, ".names {} {}\n1 1", signal(f, idx, &input), name)),
+        }
+    }
+    writeln!(writer, ".end")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::anf::{VariableNames, parse_anf};
+
+    use std::collections::{HashMap, HashSet};
+    use std::io;
+
+    fn verilog_tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() { continue }
+            let mut token = c.to_string();
+            if c == '\\' {
+                while let Some(c) = chars.next() {
+                    if c.is_whitespace() { break }
+                    token.push(c);
+                }
+            } else if c.is_alphanumeric() || c == '_' || c == '\'' {
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '\'') { break }
+                    token.push(c);
+                    chars.next();
+                }
+            }
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    /// Runs the `assign` statements of a module written by `write_verilog`,
+    /// which are in dependency order.
+    fn simulate_verilog(text: &str, mut values: HashMap<String, bool>) -> HashMap<String, bool> {
+        for line in text.lines().map(|line| line.trim()).filter(|line| line.starts_with("assign ")) {
+            let tokens = verilog_tokens(&line["assign ".len()..line.len() - 1]);
+            let value = |token: &str| match token {
+                "1'b0" => false,
+                "1'b1" => true,
+                name => values[name],
+            };
+            // A term, or a term XOR another signal.
+            let result = match tokens.len() {
+                3 => value(&tokens[2]),
+                5 if tokens[3] == "&" => value(&tokens[2]) & value(&tokens[4]),
+                5 => value(&tokens[2]) ^ value(&tokens[4]),
+                7 => (value(&tokens[2]) & value(&tokens[4])) ^ value(&tokens[6]),
+                _ => panic!("{}", line),
+            };
+            values.insert(tokens[0].clone(), result);
+        }
+        values
+    }
+
+    fn simulate_blif(text: &str, mut values: HashMap<String, bool>) -> HashMap<String, bool> {
+        let mut lines = text.lines().peekable();
+        while let Some(line) = lines.next() {
+            if !line.starts_with(".names") { continue }
+            let mut signals: Vec<&str> = line.split_whitespace().skip(1).collect();
+            let output = signals.pop().unwrap();
+            let row: Vec<bool> = signals.iter().map(|&name| values[name]).collect();
+            let mut result = false;
+            while let Some(&cover) = lines.peek() {
+                if cover.starts_with('.') { break }
+                lines.next();
+                let cube = cover.split_whitespace().next().unwrap();
+                let cube = if signals.is_empty() { "" } else { cube };
+                result |= cube.chars().zip(row.iter()).all(|(c, &bit)| c == '-' || (c == '1') == bit);
+            }
+            values.insert(output.to_string(), result);
+        }
+        values
+    }
+
+    #[test]
+    fn netlist_simulation() {
+        let f = &mut Forest::new();
+        let mut names = VariableNames::new();
+        names.insert(5, "key(0)");
+        let polys = vec![parse_anf(f, "x0*x1*x2 + x0 + 1", &names).unwrap(),
+                         parse_anf(f, "x0*x1 + x2*key(0) + x1 + 1", &names).unwrap(),
+                         parse_anf(f, "x1*x2*key(0) + x2*key(0) + x1", &names).unwrap(),
+                         parse_anf(f, "x2", &names).unwrap(),
+                         parse_anf(f, "x0*x1 + x0 + x2 + 1", &names).unwrap()];
+        let outputs: Vec<(&str, NodeIdx)> = vec![("p", polys[1]), ("q", polys[2]), ("r", polys[3]),
+                                                 ("s", polys[4]), ("t", polys[0]),
+                                                 ("one", 1), ("zero", 0)];
+        let mut options = NetlistOptions::new();
+        options.names = names.clone();
+
+        let mut verilog = Vec::new();
+        write_verilog(f, &mut verilog, &outputs, &options).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        assert!(verilog.starts_with("module boo(x0, x1, x2, \\key(0) , p, q, r, s, t, one, zero);\n"));
+        assert!(verilog.contains("  assign r = x2;\n"));
+        let mut blif = Vec::new();
+        write_blif(f, &mut blif, &outputs, &options).unwrap();
+        let blif = String::from_utf8(blif).unwrap();
+        assert!(blif.starts_with(".model boo\n.inputs x0 x1 x2 key(0)\n.outputs p q r s t one zero\n"));
+
+        // Shared nodes are declared once.
+        let wires: Vec<&str> = verilog.lines().filter(|line| line.contains(" wire ")).collect();
+        let unique: HashSet<&str> = wires.iter().cloned().collect();
+        assert_eq!(wires.len(), unique.len());
+
+        let vars: [Variable; 4] = [0, 1, 2, 5];
+        for assignment in 0..16 {
+            let set: HashSet<Variable> = (0..4).filter(|i| assignment >> i & 1 == 1).map(|i| vars[i]).collect();
+            let inputs = |escape: bool| -> HashMap<String, bool> {
+                vars.iter().map(|&var| {
+                    let name = names.name(var);
+                    let name = if escape && var == 5 { format!("\\{}", name) } else { name };
+                    (name, set.contains(&var))
+                }).collect()
+            };
+            let from_verilog = simulate_verilog(&verilog, inputs(true));
+            let from_blif = simulate_blif(&blif, inputs(false));
+            for &(name, idx) in &outputs {
+                assert_eq!(from_verilog[name], f.evaluate(idx, &set));
+                assert_eq!(from_blif[name], f.evaluate(idx, &set));
+            }
+        }
+    }
+
+    #[test]
+    fn netlist_names() {
+        let f = &mut Forest::new();
+        let mut options = NetlistOptions::new();
+        options.names.insert(0, "a b");
+        options.names.insert(1, "n1f");
+        options.names.insert(2, "wire");
+        let p = parse_anf(f, "x0*x1 + x2", &VariableNames::new()).unwrap();
+        let write = |outputs: &[(&str, NodeIdx)], options: &NetlistOptions| {
+            let mut out = Vec::new();
+            write_verilog(f, &mut out, outputs, options).map(|_| String::from_utf8(out).unwrap())
+        };
+
+        // Whitespace would end an escaped identifier and n1f is a gate wire,
+        // so those inputs get their default names.
+        let verilog = write(&[("p", p)], &options).unwrap();
+        assert!(verilog.starts_with("module boo(x0, x1, \\wire , p);\n"));
+        let mut blif = Vec::new();
+        write_blif(f, &mut blif, &[("p", p)], &options).unwrap();
+        assert!(String::from_utf8(blif).unwrap().starts_with(".model boo\n.inputs a_b x1 wire\n"));
+
+        for outputs in vec![vec![("p q", p)], vec![("n2", p)], vec![("x0", p)], vec![("p", p), ("p", 0)]] {
+            assert_eq!(write(&outputs, &options).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        options.module = "my module".to_string();
+        assert_eq!(write(&[("p", p)], &options).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,5 +1,7 @@
 use super::node::NodeIdx;
 
+use std::mem::replace;
+
 const HIGH_BIT: NodeIdx = 0x8000_0000_0000_0000;
 const DEFAULT_SIZE: usize = 256;
 const MAX_DELTA: usize = 8;
@@ -9,6 +11,7 @@ pub struct NodePage {
     // idx, hi, lo
     locations: Vec<(NodeIdx, NodeIdx, NodeIdx)>,
     size: usize,
+    newest: NodeIdx,
 }
 
 impl NodePage {
@@ -16,6 +19,45 @@ impl NodePage {
         NodePage {
             locations: (0..DEFAULT_SIZE).map(|_| (HIGH_BIT, 0, 0)).collect(),
             size: DEFAULT_SIZE,
+            newest: 0,
+        }
+    }
+
+    fn hash(&self, hi: NodeIdx, lo: NodeIdx) -> usize {
+        // Both children have to reach the low bits, nodes sharing a high
+        // branch would otherwise all land on the same slot.
+        let hash = hi.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ lo.wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        (hash ^ (hash >> 29)) & (self.size - 1)
+    }
+
+    /// Rehashes into `size` slots, keeping the nodes below `len`.
+    fn rebuild(&mut self, size: usize, len: NodeIdx) {
+        self.size = size;
+        self.newest = 0;
+        let old = replace(&mut self.locations, (0..self.size).map(|_| (HIGH_BIT, 0, 0)).collect());
+
+        for (idx, hi, lo) in old {
+            if idx >= len { continue }
+            if idx > self.newest { self.newest = idx }
+
+            let mut loc = self.hash(hi, lo);
+            while self.locations[loc].0 < HIGH_BIT {
+                loc = (loc + 1) & (self.size - 1);
+            }
+            self.locations[loc] = (idx, hi, lo);
+        }
+    }
+
+    fn grow(&mut self) {
+        let size = self.size << 1;
+        self.rebuild(size, HIGH_BIT);
+    }
+
+    /// Forgets the nodes from `len` on, after the forest dropped them.
+    pub fn truncate(&mut self, len: NodeIdx) {
+        if self.newest >= len {
+            let size = self.size;
+            self.rebuild(size, len);
         }
     }
 
@@ -24,13 +66,12 @@ impl NodePage {
             panic!("Overflowing into high bit on next NodeIdx!");
         }
 
-        let hash = (hi ^ (lo << 32) ^ (lo >> 32)) & (self.size - 1);
+        let hash = self.hash(hi, lo);
         let mut delta = 0;
 
         loop {
             if delta > MAX_DELTA {
-                self.size <<= 1;
-                self.locations.resize(self.size, (HIGH_BIT, 0, 0));
+                self.grow();
                 return self.get_or_insert(hi, lo, next_free);
             }
 
@@ -39,6 +80,7 @@ impl NodePage {
 
             if idx >= HIGH_BIT {
                 self.locations[loc] = (next_free, hi, lo);
+                self.newest = next_free;
                 return next_free;
             }
 
@@ -52,3 +94,37 @@ impl NodePage {
         // *self.locations.entry((hi, lo)).or_insert(next_free)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn node_page_shared_hi() {
+        // Nodes that differ only in their low branch must not all probe the
+        // same slots.
+        let mut page = NodePage::new();
+        for lo in 0..2000 {
+            assert_eq!(page.get_or_insert(7, lo, 100 + lo), 100 + lo);
+        }
+        for lo in 0..2000 {
+            assert_eq!(page.get_or_insert(7, lo, 5000), 100 + lo);
+        }
+        assert!(page.size <= 8192);
+    }
+
+    #[test]
+    fn node_page_grow() {
+        // Enough nodes to overflow MAX_DELTA probes many times over; every
+        // node has to be found again after the table grew.
+        let mut page = NodePage::new();
+        for i in 0..20000 {
+            assert_eq!(page.get_or_insert(i + 2, 2 * i, 100 + i), 100 + i);
+        }
+        assert!(page.size > DEFAULT_SIZE);
+        for i in 0..20000 {
+            assert_eq!(page.get_or_insert(i + 2, 2 * i, 50000), 100 + i);
+        }
+        assert!(page.size <= 1 << 19);
+    }
+}
@@ -0,0 +1,168 @@
+use super::node::NodeIdx;
+use super::forest::Forest;
+use super::add::add;
+use super::monomial::Monomial;
+use super::ordering::MonomialOrdering;
+use super::divide::{multiply_by_monomial, substitute, subset1};
+
+use std::cmp::Ordering;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Reduction {
+    /// Stop as soon as the leading monomial is irreducible.
+    Top,
+    /// Reduce every monomial of the result.
+    Full,
+}
+
+/// Pairs each non-zero polynomial of `basis` with its leading monomial.
+pub fn with_leads<O>(f: &Forest, basis: &[NodeIdx], ordering: &O) -> Vec<(Monomial, NodeIdx)>
+    where O: MonomialOrdering
+{
+    basis.iter()
+         .filter_map(|&g| ordering.leading_monomial(f, g).map(|lead| (lead, g)))
+         .collect()
+}
+
+/// Multivariate division of `idx` by `leads`, as built by `with_leads`.
+pub fn reduce<O>(f: &mut Forest,
+                 idx: NodeIdx,
+                 leads: &[(Monomial, NodeIdx)],
+                 ordering: &O,
+                 reduction: Reduction) -> NodeIdx
+    where O: MonomialOrdering
+{
+    let mut p = idx;
+    let mut r = 0;
+
+    while let Some(lm) = ordering.leading_monomial(f, p) {
+        match leads.iter().find(|&&(ref lead, _)| lead.divides(&lm)) {
+            Some(&(ref lead, g)) => {
+                let tg = multiply_by_monomial(f, g, &lm.without(lead));
+                p = add(f, p, tg);
+            }
+            None => {
+                if reduction == Reduction::Top { break }
+
+                let m = lm.to_node_idx(f);
+                p = add(f, p, m);
+                r = add(f, r, m);
+            }
+        }
+    }
+
+    add(f, p, r)
+}
+
+/// The normal form of `idx` modulo `basis` in the Boolean ring. The field
+/// equations `x^2 = x` need no special treatment as the forest only holds
+/// square-free monomials.
+pub fn normal_form<O>(f: &mut Forest,
+                      idx: NodeIdx,
+                      basis: &[NodeIdx],
+                      ordering: &O,
+                      reduction: Reduction) -> NodeIdx
+    where O: MonomialOrdering
+{
+    let leads = with_leads(f, basis, ordering);
+    reduce(f, idx, &leads, ordering, reduction)
+}
+
+/// Fully reduces `idx` by substituting each linear leading variable with the
+/// rest of its polynomial. Falls back to `normal_form` when some leading
+/// monomial is not linear.
+pub fn linear_normal_form<O>(f: &mut Forest,
+                             idx: NodeIdx,
+                             basis: &[NodeIdx],
+                             ordering: &O) -> NodeIdx
+    where O: MonomialOrdering
+{
+    let mut leads = with_leads(f, basis, ordering);
+
+    if leads.iter().any(|&(ref lead, _)| lead.degree() == 0) {
+        return 0;
+    }
+    if leads.iter().any(|&(ref lead, _)| lead.degree() > 1) {
+        return reduce(f, idx, &leads, ordering, Reduction::Full);
+    }
+
+    // Substituting the largest variables first only ever introduces smaller
+    // ones, so a single pass suffices.
+    leads.sort_by(|a, b| match ordering.compare(&b.0, &a.0) {
+        Ordering::Equal => a.1.cmp(&b.1),
+        ordering => ordering,
+    });
+
+    let mut p = idx;
+    for &(ref lead, g) in &leads {
+        let var = lead.variables()[0];
+        if subset1(f, p, var) == 0 { continue }
+
+        let lead = lead.to_node_idx(f);
+        let tail = add(f, g, lead);
+        p = substitute(f, p, var, tail);
+    }
+
+    p
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::ordering::{Lex, DegLex, DegRevLex};
+
+    #[test]
+    fn normal_form_basic() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x1x2 = multiply(f, x[1], x[2]);
+        let g0 = add(f, x[0], x[3]);
+        let g1 = add(f, x1x2, 1);
+        let basis = vec![g0, g1];
+
+        // x0*x1*x2 + x1*x2 + x3  ->  x3 + 1 + x3  ->  1
+        let x0x1x2 = multiply(f, x[0], x1x2);
+        let p = add(f, x0x1x2, x1x2);
+        let p = add(f, p, x[3]);
+
+        assert_eq!(normal_form(f, p, &basis, &DegLex, Reduction::Full), 1);
+        assert_eq!(normal_form(f, p, &basis, &DegRevLex, Reduction::Full), 1);
+
+        // x0 + x1 + x3  ->  x1, which no leading term divides.
+        let q = add(f, x[1], x[3]);
+        let q = add(f, q, x[0]);
+        let top = normal_form(f, q, &basis, &Lex, Reduction::Top);
+        assert_eq!(top, x[1]);
+        assert_eq!(normal_form(f, x[2], &basis, &Lex, Reduction::Top), x[2]);
+        assert_eq!(normal_form(f, p, &[1], &Lex, Reduction::Full), 0);
+    }
+
+    #[test]
+    fn normal_form_linear() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..5).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let g0 = add(f, x[0], x[1]);
+        let g0 = add(f, g0, 1);
+        let g1 = add(f, x[1], x[3]);
+        let g2 = add(f, x[2], x[4]);
+        let basis = vec![g2, g0, g1];
+
+        let x0x2 = multiply(f, x[0], x[2]);
+        let x1x4 = multiply(f, x[1], x[4]);
+        let p = add(f, x0x2, x1x4);
+        let p = add(f, p, x[3]);
+
+        for &p in &[p, x0x2, x1x4, 1, 0] {
+            let full = normal_form(f, p, &basis, &DegLex, Reduction::Full);
+            assert_eq!(linear_normal_form(f, p, &basis, &DegLex), full);
+            let full = normal_form(f, p, &basis, &Lex, Reduction::Full);
+            assert_eq!(linear_normal_form(f, p, &basis, &Lex), full);
+        }
+    }
+}
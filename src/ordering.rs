@@ -0,0 +1,286 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::monomial::{Monomial, monomials, monomials_of_degree};
+
+use std::cmp::Ordering;
+
+/// A total order on monomials. Variables are ranked `x0 > x1 > ...`, which
+/// is the order the forest stores them in.
+pub trait MonomialOrdering {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering;
+
+    fn leading_monomial(&self, f: &Forest, idx: NodeIdx) -> Option<Monomial> {
+        monomials(f, idx).fold(None, |best, m| match best {
+            Some(best) => if self.compare(&m, &best) == Ordering::Greater {
+                    Some(m)
+                } else {
+                    Some(best)
+                },
+            None => Some(m),
+        })
+    }
+}
+
+impl<'a, O: MonomialOrdering + ?Sized> MonomialOrdering for &'a O {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        (**self).compare(lhs, rhs)
+    }
+
+    fn leading_monomial(&self, f: &Forest, idx: NodeIdx) -> Option<Monomial> {
+        (**self).leading_monomial(f, idx)
+    }
+}
+
+fn then<F: FnOnce() -> Ordering>(ordering: Ordering, next: F) -> Ordering {
+    match ordering {
+        Ordering::Equal => next(),
+        ordering => ordering,
+    }
+}
+
+fn lex(lhs: &[Variable], rhs: &[Variable]) -> Ordering {
+    for (x, y) in lhs.iter().zip(rhs.iter()) {
+        if x != y { return y.cmp(x) }
+    }
+    lhs.len().cmp(&rhs.len())
+}
+
+fn revlex(lhs: &[Variable], rhs: &[Variable]) -> Ordering {
+    for (x, y) in lhs.iter().rev().zip(rhs.iter().rev()) {
+        if x != y { return y.cmp(x) }
+    }
+    Ordering::Equal
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Lex;
+
+impl MonomialOrdering for Lex {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        lex(lhs.variables(), rhs.variables())
+    }
+
+    // Following the high branches gives the lex-largest path.
+    fn leading_monomial(&self, f: &Forest, idx: NodeIdx) -> Option<Monomial> {
+        if idx == 0 { return None }
+
+        let mut vars = Vec::new();
+        let mut idx = idx;
+        while idx > 1 {
+            let Node(var, hi, _) = f.to_node(idx);
+            vars.push(var);
+            idx = hi;
+        }
+
+        Some(Monomial::from_vars(&vars))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DegLex;
+
+impl MonomialOrdering for DegLex {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        then(lhs.degree().cmp(&rhs.degree()),
+             || lex(lhs.variables(), rhs.variables()))
+    }
+
+    // Take the high branch whenever it can still reach the maximal degree.
+    fn leading_monomial(&self, f: &Forest, idx: NodeIdx) -> Option<Monomial> {
+        if idx == 0 { return None }
+
+        let mut vars = Vec::new();
+        let mut idx = idx;
+        let mut degree = f.degree(idx);
+        while idx > 1 {
+            let Node(var, hi, lo) = f.to_node(idx);
+            if degree > 0 && f.degree(hi) + 1 == degree {
+                vars.push(var);
+                idx = hi;
+                degree -= 1;
+            } else {
+                idx = lo;
+            }
+        }
+
+        Some(Monomial::from_vars(&vars))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DegRevLex;
+
+impl MonomialOrdering for DegRevLex {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        then(lhs.degree().cmp(&rhs.degree()),
+             || revlex(lhs.variables(), rhs.variables()))
+    }
+
+    fn leading_monomial(&self, f: &Forest, idx: NodeIdx) -> Option<Monomial> {
+        if idx == 0 { return None }
+
+        monomials_of_degree(f, idx, f.degree(idx)).fold(None, |best, m| match best {
+            Some(best) => if revlex(m.variables(), best.variables()) == Ordering::Greater {
+                    Some(m)
+                } else {
+                    Some(best)
+                },
+            None => Some(m),
+        })
+    }
+}
+
+/// Compares the variables below `boundary` with `first`, breaking ties on
+/// the remaining variables with `second`.
+#[derive(Debug, Copy, Clone)]
+pub struct Block<A, B> {
+    pub boundary: Variable,
+    pub first: A,
+    pub second: B,
+}
+
+impl<A, B> Block<A, B> {
+    pub fn new(boundary: Variable, first: A, second: B) -> Block<A, B> {
+        Block { boundary: boundary, first: first, second: second }
+    }
+}
+
+impl<A: MonomialOrdering, B: MonomialOrdering> MonomialOrdering for Block<A, B> {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        let (lhs_first, lhs_second) = split(lhs, |var| var < self.boundary);
+        let (rhs_first, rhs_second) = split(rhs, |var| var < self.boundary);
+
+        then(self.first.compare(&lhs_first, &rhs_first),
+             || self.second.compare(&lhs_second, &rhs_second))
+    }
+}
+
+/// An elimination ordering: any monomial containing one of `eliminate` is
+/// larger than every monomial free of them. Both parts use `inner`.
+#[derive(Debug, Clone)]
+pub struct Elimination<O> {
+    eliminate: Vec<Variable>,
+    inner: O,
+}
+
+impl<O> Elimination<O> {
+    pub fn new(eliminate: &[Variable], inner: O) -> Elimination<O> {
+        let mut eliminate = eliminate.to_vec();
+        eliminate.sort();
+        eliminate.dedup();
+        Elimination { eliminate: eliminate, inner: inner }
+    }
+}
+
+impl<O: MonomialOrdering> MonomialOrdering for Elimination<O> {
+    fn compare(&self, lhs: &Monomial, rhs: &Monomial) -> Ordering {
+        let eliminated = |var| self.eliminate.binary_search(&var).is_ok();
+        let (lhs_elim, lhs_rest) = split(lhs, &eliminated);
+        let (rhs_elim, rhs_rest) = split(rhs, &eliminated);
+
+        then(self.inner.compare(&lhs_elim, &rhs_elim),
+             || self.inner.compare(&lhs_rest, &rhs_rest))
+    }
+}
+
+fn split<P>(m: &Monomial, pred: P) -> (Monomial, Monomial)
+    where P: Fn(Variable) -> bool
+{
+    let (yes, no): (Vec<Variable>, Vec<Variable>) = m.variables()
+        .iter()
+        .partition(|&&var| pred(var));
+    (Monomial::from_vars(&yes), Monomial::from_vars(&no))
+}
+
+pub fn leading_monomial<O>(f: &Forest, idx: NodeIdx, ordering: &O) -> Option<Monomial>
+    where O: MonomialOrdering
+{
+    ordering.leading_monomial(f, idx)
+}
+
+/// The leading monomial as a polynomial, or 0 for the zero polynomial.
+pub fn lead_term<O>(f: &mut Forest, idx: NodeIdx, ordering: &O) -> NodeIdx
+    where O: MonomialOrdering
+{
+    match ordering.leading_monomial(f, idx) {
+        Some(m) => m.to_node_idx(f),
+        None => 0,
+    }
+}
+
+pub fn tail<O>(f: &mut Forest, idx: NodeIdx, ordering: &O) -> NodeIdx
+    where O: MonomialOrdering
+{
+    let lead = lead_term(f, idx, ordering);
+    add(f, idx, lead)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::Node;
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::monomial::{Monomial, monomials};
+
+    use std::cmp::Ordering;
+
+    fn check_leading<O: MonomialOrdering>(f: &Forest, idx: NodeIdx, ordering: &O) {
+        let mut terms: Vec<Monomial> = monomials(f, idx).collect();
+        terms.sort_by(|a, b| ordering.compare(b, a));
+        assert_eq!(ordering.leading_monomial(f, idx), terms.first().cloned());
+    }
+
+    #[test]
+    fn ordering_compare() {
+        let x0x3 = Monomial::from_vars(&[0, 3]);
+        let x1x2 = Monomial::from_vars(&[1, 2]);
+        let x1 = Monomial::from_vars(&[1]);
+
+        assert_eq!(Lex.compare(&x0x3, &x1x2), Ordering::Greater);
+        assert_eq!(Lex.compare(&x1, &x1x2), Ordering::Less);
+        assert_eq!(DegLex.compare(&x0x3, &x1x2), Ordering::Greater);
+        assert_eq!(DegRevLex.compare(&x0x3, &x1x2), Ordering::Less);
+        assert_eq!(DegRevLex.compare(&x1, &x1x2), Ordering::Less);
+
+        let block = Block::new(2, DegLex, Lex);
+        assert_eq!(block.compare(&Monomial::from_vars(&[0]), &x1x2), Ordering::Greater);
+        assert_eq!(block.compare(&x1, &x0x3), Ordering::Less);
+
+        let elim = Elimination::new(&[3], DegLex);
+        assert_eq!(elim.compare(&x0x3, &x1x2), Ordering::Greater);
+        assert_eq!(elim.compare(&Monomial::from_vars(&[3]), &x1x2), Ordering::Greater);
+    }
+
+    #[test]
+    fn ordering_leading() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..5).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x4 = multiply(f, x[0], x[4]);
+        let x1x2 = multiply(f, x[1], x[2]);
+        let x1x2x3 = multiply(f, x1x2, x[3]);
+        let p = add(f, x0x4, x1x2);
+        let p = add(f, p, x[0]);
+        let p = add(f, p, 1);
+
+        check_leading(f, p, &Lex);
+        check_leading(f, p, &DegLex);
+        check_leading(f, p, &DegRevLex);
+        check_leading(f, p, &Block::new(1, Lex, DegRevLex));
+
+        assert_eq!(Lex.leading_monomial(f, p), Some(Monomial::from_vars(&[0, 4])));
+        assert_eq!(DegRevLex.leading_monomial(f, p), Some(Monomial::from_vars(&[1, 2])));
+
+        let q = add(f, p, x1x2x3);
+        check_leading(f, q, &DegLex);
+        assert_eq!(lead_term(f, q, &DegLex), x1x2x3);
+
+        let t = tail(f, q, &DegLex);
+        assert_eq!(add(f, t, x1x2x3), q);
+        assert_eq!(lead_term(f, 0, &Lex), 0);
+        assert_eq!(lead_term(f, 1, &DegRevLex), 1);
+    }
+}
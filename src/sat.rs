@@ -0,0 +1,655 @@
+use super::node::Variable;
+use super::cnf::{Cnf, Literal};
+use super::gf2::BitMatrix;
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem::replace;
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[derive(Debug)]
+pub enum SatError {
+    Io(io::Error),
+    /// The solver gave up.
+    Unknown,
+    /// Solver output that could not be understood.
+    Output(String),
+}
+
+impl fmt::Display for SatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SatError::Io(ref err) => write!(f, "{}", err),
+            SatError::Unknown => write!(f, "solver returned no answer"),
+            SatError::Output(ref line) => write!(f, "unexpected solver output {:?}", line),
+        }
+    }
+}
+
+impl Error for SatError {
+    fn description(&self) -> &str {
+        match *self {
+            SatError::Io(ref err) => err.description(),
+            SatError::Unknown => "SAT solver gave up",
+            SatError::Output(_) => "malformed SAT solver output",
+        }
+    }
+}
+
+impl From<io::Error> for SatError {
+    fn from(err: io::Error) -> SatError {
+        SatError::Io(err)
+    }
+}
+
+pub trait SatBackend {
+    /// A model of `cnf` as DIMACS literals, or `None` if it has none.
+    fn model(&mut self, cnf: &Cnf) -> Result<Option<Vec<Literal>>, SatError>;
+
+    /// The true variables of a model of `cnf`, that is a common zero of the
+    /// polynomials it encodes.
+    fn solve(&mut self, cnf: &Cnf) -> Result<Option<HashSet<Variable>>, SatError> {
+        Ok(try!(self.model(cnf)).map(|model| cnf.decode(&model)))
+    }
+}
+
+/// A small CDCL solver: two watched literals, first-UIP learning, VSIDS
+/// with phase saving and restarts. XOR clauses are brought into reduced
+/// echelon form up front and propagated natively through two watched
+/// variables each.
+#[derive(Debug, Clone)]
+pub struct Cdcl {
+    /// Give up with `SatError::Unknown` after this many conflicts.
+    pub conflict_limit: Option<usize>,
+}
+
+impl Cdcl {
+    pub fn new() -> Cdcl {
+        Cdcl { conflict_limit: None }
+    }
+}
+
+impl SatBackend for Cdcl {
+    fn model(&mut self, cnf: &Cnf) -> Result<Option<Vec<Literal>>, SatError> {
+        let mut solver = match Solver::new(cnf) {
+            Some(solver) => solver,
+            None => return Ok(None),
+        };
+        match solver.search(self.conflict_limit) {
+            Some(true) => Ok(Some(solver.model())),
+            Some(false) => Ok(None),
+            None => Err(SatError::Unknown),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Reason {
+    Decision,
+    Clause(usize),
+    Xor(usize),
+}
+
+/// Literals are `2 * var + negated` over variables from zero.
+struct Solver {
+    clauses: Vec<Vec<usize>>,
+    /// Clauses by the literal whose falsification they wait for.
+    watches: Vec<Vec<usize>>,
+    /// Variables and the value of their sum. The first two are watched.
+    xors: Vec<(Vec<usize>, bool)>,
+    xor_watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Reason>,
+    trail: Vec<usize>,
+    trail_lim: Vec<usize>,
+    head: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    phases: Vec<bool>,
+}
+
+fn literal(dimacs: Literal) -> usize {
+    2 * (dimacs.abs() as usize - 1) + (dimacs < 0) as usize
+}
+
+impl Solver {
+    /// `None` if the clauses are inconsistent on their own.
+    fn new(cnf: &Cnf) -> Option<Solver> {
+        let n = cnf.variables().len();
+        let mut solver = Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * n],
+            xors: Vec::new(),
+            xor_watches: vec![Vec::new(); n],
+            values: vec![None; n],
+            levels: vec![0; n],
+            reasons: vec![Reason::Decision; n],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            head: 0,
+            activity: vec![0.0; n],
+            increment: 1.0,
+            phases: vec![false; n],
+        };
+
+        for clause in cnf.clauses() {
+            let mut lits: Vec<usize> = clause.iter().map(|&dimacs| literal(dimacs)).collect();
+            lits.sort();
+            lits.dedup();
+            if lits.windows(2).any(|w| w[0] ^ 1 == w[1]) { continue }
+            if !solver.add_clause(lits) { return None }
+        }
+
+        if !cnf.xors().is_empty() {
+            // Reduced echelon form leaves every pivot in one XOR only, and
+            // shows inconsistencies at once.
+            let mut matrix = BitMatrix::new(cnf.xors().len(), n + 1);
+            for (row, &(ref vars, value)) in cnf.xors().iter().enumerate() {
+                for &var in vars {
+                    let set = matrix.get(row, var - 1);
+                    matrix.set(row, var - 1, !set);
+                }
+                matrix.set(row, n, value);
+            }
+            let pivots = matrix.echelonize(true);
+
+            for (row, &pivot) in pivots.iter().enumerate() {
+                if pivot == n { return None }
+                let vars: Vec<usize> = (0..n).filter(|&var| matrix.get(row, var)).collect();
+                if !solver.add_xor(vars, matrix.get(row, n)) { return None }
+            }
+        }
+
+        if solver.propagate().is_some() { return None }
+        Some(solver)
+    }
+
+    fn value(&self, lit: usize) -> Option<bool> {
+        self.values[lit >> 1].map(|value| value != (lit & 1 == 1))
+    }
+
+    fn level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: usize, reason: Reason) {
+        let var = lit >> 1;
+        self.values[var] = Some(lit & 1 == 0);
+        self.levels[var] = self.level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Adds a clause at level zero, false if it is already violated.
+    fn add_clause(&mut self, lits: Vec<usize>) -> bool {
+        match lits.len() {
+            0 => false,
+            1 => match self.value(lits[0]) {
+                Some(value) => value,
+                None => {
+                    self.enqueue(lits[0], Reason::Decision);
+                    true
+                }
+            },
+            _ => {
+                let index = self.clauses.len();
+                self.watches[lits[0]].push(index);
+                self.watches[lits[1]].push(index);
+                self.clauses.push(lits);
+                true
+            }
+        }
+    }
+
+    fn add_xor(&mut self, vars: Vec<usize>, value: bool) -> bool {
+        match vars.len() {
+            0 => !value,
+            1 => self.add_clause(vec![2 * vars[0] + !value as usize]),
+            _ => {
+                let index = self.xors.len();
+                self.xor_watches[vars[0]].push(index);
+                self.xor_watches[vars[1]].push(index);
+                self.xors.push((vars, value));
+                true
+            }
+        }
+    }
+
+    /// The clause that forced `var`, or a violated one for a conflict.
+    fn explain(&self, reason: Reason) -> Vec<usize> {
+        match reason {
+            Reason::Clause(c) => self.clauses[c].clone(),
+            // Every variable of the XOR at its current value, negated.
+            Reason::Xor(x) => self.xors[x].0.iter().map(|&var| {
+                2 * var + self.values[var].unwrap() as usize
+            }).collect(),
+            Reason::Decision => unreachable!(),
+        }
+    }
+
+    /// Propagates the trail, returning the conflict if one is reached.
+    fn propagate(&mut self) -> Option<Reason> {
+        while self.head < self.trail.len() {
+            let lit = self.trail[self.head];
+            self.head += 1;
+
+            if let Some(conflict) = self.propagate_clauses(lit ^ 1) {
+                return Some(conflict);
+            }
+            if let Some(conflict) = self.propagate_xors(lit >> 1) {
+                return Some(conflict);
+            }
+        }
+        None
+    }
+
+    fn propagate_clauses(&mut self, false_lit: usize) -> Option<Reason> {
+        let watching = replace(&mut self.watches[false_lit], Vec::new());
+        let mut kept = Vec::with_capacity(watching.len());
+        let mut conflict = None;
+
+        for (i, &c) in watching.iter().enumerate() {
+            if conflict.is_some() {
+                kept.extend(watching[i..].iter().cloned());
+                break;
+            }
+
+            if self.clauses[c][0] == false_lit {
+                self.clauses[c].swap(0, 1);
+            }
+            let first = self.clauses[c][0];
+            if self.value(first) == Some(true) {
+                kept.push(c);
+                continue;
+            }
+
+            let replacement = (2..self.clauses[c].len())
+                .find(|&k| self.value(self.clauses[c][k]) != Some(false));
+            match replacement {
+                Some(k) => {
+                    self.clauses[c].swap(1, k);
+                    let watch = self.clauses[c][1];
+                    self.watches[watch].push(c);
+                }
+                None => {
+                    kept.push(c);
+                    if self.value(first) == Some(false) {
+                        conflict = Some(Reason::Clause(c));
+                    } else {
+                        self.enqueue(first, Reason::Clause(c));
+                    }
+                }
+            }
+        }
+
+        self.watches[false_lit] = kept;
+        conflict
+    }
+
+    fn propagate_xors(&mut self, var: usize) -> Option<Reason> {
+        let watching = replace(&mut self.xor_watches[var], Vec::new());
+        let mut kept = Vec::with_capacity(watching.len());
+        let mut conflict = None;
+
+        for (i, &x) in watching.iter().enumerate() {
+            if conflict.is_some() {
+                kept.extend(watching[i..].iter().cloned());
+                break;
+            }
+
+            if self.xors[x].0[0] == var {
+                self.xors[x].0.swap(0, 1);
+            }
+            let replacement = (2..self.xors[x].0.len())
+                .find(|&k| self.values[self.xors[x].0[k]].is_none());
+            if let Some(k) = replacement {
+                self.xors[x].0.swap(1, k);
+                let watch = self.xors[x].0[1];
+                self.xor_watches[watch].push(x);
+                continue;
+            }
+
+            kept.push(x);
+            let other = self.xors[x].0[0];
+            let sum = self.xors[x].0[1..].iter()
+                .fold(self.xors[x].1, |sum, &v| sum ^ self.values[v].unwrap());
+            match self.values[other] {
+                None => self.enqueue(2 * other + !sum as usize, Reason::Xor(x)),
+                Some(value) => if value != sum { conflict = Some(Reason::Xor(x)) },
+            }
+        }
+
+        self.xor_watches[var] = kept;
+        conflict
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.increment;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.increment *= 1e-100;
+        }
+    }
+
+    /// First-UIP learning: the learnt clause, asserting literal first, and
+    /// the level to go back to.
+    fn analyze(&mut self, conflict: Reason) -> (Vec<usize>, usize) {
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut reason = conflict;
+        let mut asserting: Option<usize> = None;
+
+        loop {
+            for lit in self.explain(reason) {
+                let var = lit >> 1;
+                if Some(var) == asserting.map(|lit| lit >> 1) || seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == self.level() {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+
+            loop {
+                index -= 1;
+                if seen[self.trail[index] >> 1] { break }
+            }
+            let lit = self.trail[index];
+            seen[lit >> 1] = false;
+            asserting = Some(lit);
+            pending -= 1;
+            if pending == 0 { break }
+            reason = self.reasons[lit >> 1];
+        }
+        learnt[0] = asserting.unwrap() ^ 1;
+
+        let mut back = 0;
+        for i in 1..learnt.len() {
+            if self.levels[learnt[i] >> 1] > back {
+                back = self.levels[learnt[i] >> 1];
+                learnt.swap(1, i);
+            }
+        }
+        self.increment /= 0.95;
+        (learnt, back)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level { return }
+        let start = self.trail_lim[level];
+        for &lit in &self.trail[start..] {
+            let var = lit >> 1;
+            self.phases[var] = self.values[var].unwrap();
+            self.values[var] = None;
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.head = start;
+    }
+
+    fn decide(&mut self) -> bool {
+        let mut best: Option<usize> = None;
+        for var in 0..self.values.len() {
+            if self.values[var].is_some() { continue }
+            if best.map_or(true, |best| self.activity[var] > self.activity[best]) {
+                best = Some(var);
+            }
+        }
+        match best {
+            Some(var) => {
+                self.trail_lim.push(self.trail.len());
+                let lit = 2 * var + !self.phases[var] as usize;
+                self.enqueue(lit, Reason::Decision);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the clauses are satisfiable, `None` past the conflict limit.
+    fn search(&mut self, conflict_limit: Option<usize>) -> Option<bool> {
+        let mut conflicts = 0;
+        let mut restart = 100;
+        let mut since_restart = 0;
+
+        loop {
+            match self.propagate() {
+                Some(conflict) => {
+                    if self.level() == 0 { return Some(false) }
+                    conflicts += 1;
+                    since_restart += 1;
+                    if conflict_limit.map_or(false, |limit| conflicts > limit) { return None }
+
+                    let (learnt, back) = self.analyze(conflict);
+                    self.backtrack(back);
+                    let asserting = learnt[0];
+                    if learnt.len() == 1 {
+                        self.enqueue(asserting, Reason::Decision);
+                    } else {
+                        let index = self.clauses.len();
+                        self.watches[learnt[0]].push(index);
+                        self.watches[learnt[1]].push(index);
+                        self.clauses.push(learnt);
+                        self.enqueue(asserting, Reason::Clause(index));
+                    }
+                }
+                None => {
+                    if since_restart >= restart {
+                        since_restart = 0;
+                        restart += restart / 2;
+                        self.backtrack(0);
+                    } else if !self.decide() {
+                        return Some(true);
+                    }
+                }
+            }
+        }
+    }
+
+    fn model(&self) -> Vec<Literal> {
+        self.values.iter().enumerate().map(|(var, &value)| {
+            if value == Some(true) { var as Literal + 1 } else { -(var as Literal) - 1 }
+        }).collect()
+    }
+}
+
+/// Runs a solver binary that reads DIMACS on standard input and answers in
+/// the competition format, `s` and `v` lines. XOR clauses need a solver
+/// that understands them, such as CryptoMiniSat.
+#[derive(Debug, Clone)]
+pub struct ExternalSolver {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl ExternalSolver {
+    pub fn new(command: &str) -> ExternalSolver {
+        ExternalSolver {
+            command: command.to_string(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// The answer in a solver's competition format output.
+pub fn parse_solver_output(output: &str) -> Result<Option<Vec<Literal>>, SatError> {
+    let mut satisfiable = None;
+    let mut model = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("s ") {
+            satisfiable = match line[2..].trim() {
+                "SATISFIABLE" => Some(true),
+                "UNSATISFIABLE" => Some(false),
+                "UNKNOWN" | "INDETERMINATE" => return Err(SatError::Unknown),
+                _ => return Err(SatError::Output(line.to_string())),
+            };
+        } else if line.starts_with("v ") || line == "v" {
+            for token in line[1..].split_whitespace() {
+                match token.parse::<Literal>() {
+                    Ok(0) => {}
+                    Ok(lit) => model.push(lit),
+                    Err(_) => return Err(SatError::Output(line.to_string())),
+                }
+            }
+        }
+    }
+
+    match satisfiable {
+        Some(true) => Ok(Some(model)),
+        Some(false) => Ok(None),
+        None => Err(SatError::Output(output.lines().last().unwrap_or("").to_string())),
+    }
+}
+
+impl SatBackend for ExternalSolver {
+    /// The DIMACS is fed to the solver from another thread while its output
+    /// is read, as a solver may write more than a pipe holds before it has
+    /// read all of its input.
+    fn model(&mut self, cnf: &Cnf) -> Result<Option<Vec<Literal>>, SatError> {
+        let mut dimacs = Vec::new();
+        try!(cnf.write_dimacs(&mut dimacs));
+
+        let mut child = try!(Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn());
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = thread::spawn(move || {
+            try!(stdin.write_all(&dimacs));
+            stdin.flush()
+        });
+
+        let output = try!(child.wait_with_output());
+        match writer.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => return Err(SatError::Io(err)),
+            Err(_) => return Err(SatError::Io(io::Error::new(io::ErrorKind::Other, "writer thread panicked"))),
+        }
+        parse_solver_output(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::cnf::{Cnf, CnfOptions, DimacsOptions, read_dimacs};
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn sat_matches_brute_force() {
+        let mut lfsr: u32 = 11;
+
+        for round in 0..40 {
+            let f = &mut Forest::new();
+            let polys: Vec<NodeIdx> = (0..10).map(|_| {
+                let mut p = 0;
+                for _ in 0..4 {
+                    lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let a = f.to_node_idx(Node(((lfsr >> 16) % 10) as Variable, 1, 0));
+                    let b = f.to_node_idx(Node(((lfsr >> 24) % 10) as Variable, 1, 0));
+                    let term = if lfsr & 0x300 != 0 { multiply(f, a, b) } else { a };
+                    p = add(f, p, term);
+                }
+                if lfsr & 0x10000 != 0 { add(f, p, 1) } else { p }
+            }).collect();
+
+            let satisfiable = (0..1024u32).any(|mask| {
+                let solution: HashSet<Variable> = (0..10).filter(|&i| mask >> i & 1 == 1).collect();
+                polys.iter().all(|&p| !f.evaluate(p, &solution))
+            });
+
+            let mut options = CnfOptions::new();
+            options.karnaugh_limit = if round % 2 == 0 { 0 } else { 4 };
+            options.xor_clauses = round % 3 == 0;
+            let cnf = Cnf::from_polynomials(f, &polys, &options);
+
+            match Cdcl::new().solve(&cnf).unwrap() {
+                Some(solution) => assert!(polys.iter().all(|&p| !f.evaluate(p, &solution))),
+                None => assert!(!satisfiable),
+            }
+        }
+    }
+
+    #[test]
+    fn sat_pigeonhole() {
+        // Five pigeons in four holes, variable 4 * i + j placing pigeon i
+        // in hole j.
+        let mut text = String::new();
+        for i in 0..5 {
+            let holes: Vec<String> = (0..4).map(|j| format!("{}", 4 * i + j + 1)).collect();
+            text.push_str(&format!("{} 0\n", holes.join(" ")));
+        }
+        for j in 0..4 {
+            for a in 0..5 {
+                for b in a + 1..5 {
+                    text.push_str(&format!("-{} -{} 0\n", 4 * a + j + 1, 4 * b + j + 1));
+                }
+            }
+        }
+
+        let f = &mut Forest::new();
+        let polys = read_dimacs(f, text.as_bytes(), &DimacsOptions { group_monomials: 0 }).unwrap();
+        let mut options = CnfOptions::new();
+        options.karnaugh_limit = 0;
+        let cnf = Cnf::from_polynomials(f, &polys, &options);
+        assert_eq!(Cdcl::new().solve(&cnf).unwrap(), None);
+
+        match (Cdcl { conflict_limit: Some(1) }).solve(&cnf) {
+            Err(SatError::Unknown) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn sat_solver_output() {
+        let output = "c comment\ns SATISFIABLE\nv 1 -2\nv 3 0\n";
+        assert_eq!(parse_solver_output(output).unwrap(), Some(vec![1, -2, 3]));
+        assert_eq!(parse_solver_output("s UNSATISFIABLE\n").unwrap(), None);
+        match parse_solver_output("s UNKNOWN\n") {
+            Err(SatError::Unknown) => {}
+            other => panic!("{:?}", other),
+        }
+        match parse_solver_output("v 1 x 0\ns SATISFIABLE\n") {
+            Err(SatError::Output(_)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// A solver that fills its output pipe before it reads its input.
+    #[cfg(unix)]
+    #[test]
+    fn sat_external_output_first() {
+        let f = &mut Forest::new();
+        let polys: Vec<NodeIdx> = (0..12000).map(|i| {
+            let a = f.to_node_idx(Node((i % 100) as Variable, 1, 0));
+            let b = f.to_node_idx(Node((i / 100 + 100) as Variable, 1, 0));
+            multiply(f, a, b)
+        }).collect();
+        let cnf = Cnf::from_polynomials(f, &polys, &CnfOptions::new());
+
+        let mut solver = ExternalSolver::new("sh");
+        solver.args = vec!["-c".to_string(),
+                           "yes c | head -n 100000; cat > /dev/null; echo s UNSATISFIABLE".to_string()];
+        assert_eq!(solver.model(&cnf).unwrap(), None);
+    }
+}
@@ -0,0 +1,245 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial::support;
+use super::ordering::{Lex, DegLex, DegRevLex};
+use super::anf::{VariableNames, ParseAnfError, anf, parse_anf};
+
+use std::io::{self, Write};
+
+/// Term orders both SageMath and Magma know, ranking `x0 > x1 > ...` as
+/// the forest does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptOrder {
+    Lex,
+    DegLex,
+    DegRevLex,
+}
+
+fn variables(f: &Forest, polys: &[NodeIdx]) -> Vec<Variable> {
+    let mut vars: Vec<Variable> = Vec::new();
+    for &p in polys {
+        vars.extend(support(f, p));
+    }
+    vars.sort();
+    vars.dedup();
+    if vars.is_empty() { vars.push(0) }
+    vars
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with(|c: char| c.is_digit(10)) &&
+    name.chars().all(|c| (c as u32) < 128 && (c.is_alphanumeric() || c == '_'))
+}
+
+/// Names for `vars` that are valid generators in both Sage and Magma, with
+/// the names that had to be replaced and their replacements. A replacement
+/// `x<i>` is free, since no other variable can be given that name.
+fn generators(names: &VariableNames, vars: &[Variable]) -> (VariableNames, Vec<(String, String)>) {
+    let mut safe = VariableNames::new();
+    let mut renamed = Vec::new();
+    for &var in vars {
+        let name = names.name(var);
+        if is_identifier(&name) {
+            safe.insert(var, &name);
+        } else {
+            let generator = format!("x{}", var);
+            safe.insert(var, &generator);
+            renamed.push((name, generator));
+        }
+    }
+    (safe, renamed)
+}
+
+/// The ring needs a generator and the ideal an element, so neither may be
+/// empty.
+fn no_polynomials() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "no polynomials to write")
+}
+
+fn polynomials(f: &Forest, polys: &[NodeIdx], names: &VariableNames, order: ScriptOrder) -> Vec<String> {
+    polys.iter().map(|&p| {
+        let text = anf(f, p).with_names(names);
+        match order {
+            ScriptOrder::Lex => text.with_ordering(&Lex).to_string(),
+            ScriptOrder::DegLex => text.with_ordering(&DegLex).to_string(),
+            ScriptOrder::DegRevLex => text.with_ordering(&DegRevLex).to_string(),
+        }
+    }).collect()
+}
+
+/// Writes a SageMath script that computes and prints the Gröbner basis of
+/// the equations in a `BooleanPolynomialRing`. Names that are not
+/// identifiers, such as those of `VariableNames::indexed`, are replaced by
+/// `x<i>` and listed in a comment. Returns the names of the generators, to
+/// read the printed basis with `parse_basis`.
+pub fn write_sage<W: Write>(f: &Forest,
+                            writer: &mut W,
+                            polys: &[NodeIdx],
+                            names: &VariableNames,
+                            order: ScriptOrder) -> io::Result<VariableNames> {
+    if polys.is_empty() { return Err(no_polynomials()) }
+    let vars = variables(f, polys);
+    let (names, renamed) = generators(names, &vars);
+    let names = &names;
+    let gens: Vec<String> = vars.into_iter().map(|var| names.name(var)).collect();
+    let sage_order = match order {
+        ScriptOrder::Lex => "lex",
+        ScriptOrder::DegLex => "deglex",
+        ScriptOrder::DegRevLex => "degrevlex",
+    };
+
+    for &(ref name, ref generator) in &renamed {
+        try!(writeln!(writer, "# {} is {}", generator, name));
+    }
+    try!(writeln!(writer, "R.<{}> = BooleanPolynomialRing(order='{}')", gens.join(","), sage_order));
+    try!(writeln!(writer, "I = ideal(["));
+    for p in polynomials(f, polys, names, order) {
+        try!(writeln!(writer, "    {},", p));
+    }
+    try!(writeln!(writer, "])"));
+    try!(writeln!(writer, "print(I.groebner_basis())"));
+    Ok(names.clone())
+}
+
+/// Writes the same computation as a Magma script.
+pub fn write_magma<W: Write>(f: &Forest,
+                             writer: &mut W,
+                             polys: &[NodeIdx],
+                             names: &VariableNames,
+                             order: ScriptOrder) -> io::Result<VariableNames> {
+    if polys.is_empty() { return Err(no_polynomials()) }
+    let vars = variables(f, polys);
+    let (names, renamed) = generators(names, &vars);
+    let names = &names;
+    let gens: Vec<String> = vars.into_iter().map(|var| names.name(var)).collect();
+    let magma_order = match order {
+        ScriptOrder::Lex => "lex",
+        ScriptOrder::DegLex => "glex",
+        ScriptOrder::DegRevLex => "grevlex",
+    };
+
+    for &(ref name, ref generator) in &renamed {
+        try!(writeln!(writer, "// {} is {}", generator, name));
+    }
+    try!(writeln!(writer, "R<{}> := BooleanPolynomialRing({}, \"{}\");", gens.join(","), gens.len(), magma_order));
+    let polys = polynomials(f, polys, names, order);
+    try!(writeln!(writer, "I := ideal<R | {}>;", polys.join(", ")));
+    try!(writeln!(writer, "print GroebnerBasis(I);"));
+    Ok(names.clone())
+}
+
+fn offset(error: ParseAnfError, start: usize) -> ParseAnfError {
+    match error {
+        ParseAnfError::Unexpected(position) => ParseAnfError::Unexpected(start + position),
+        error => error,
+    }
+}
+
+/// Reads a list of polynomials as SageMath or Magma print it: the elements
+/// of the first bracketed, comma separated list in `text`. Anything around
+/// it, such as Sage's `Polynomial Sequence with ...` header, is skipped.
+pub fn parse_basis(f: &mut Forest, text: &str, names: &VariableNames) -> Result<Vec<NodeIdx>, ParseAnfError> {
+    let open = match text.find('[') {
+        Some(open) => open,
+        None => return Err(ParseAnfError::End),
+    };
+
+    let mut polys = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (i, c) in text[start..].char_indices().map(|(i, c)| (i + open + 1, c)) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' | ']' if depth == 0 => {
+                let element = &text[start..i];
+                if !(c == ']' && polys.is_empty() && element.trim().is_empty()) {
+                    polys.push(try!(parse_anf(f, element, names).map_err(|error| offset(error, start))));
+                }
+                if c == ']' { return Ok(polys) }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    Err(ParseAnfError::End)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::forest::Forest;
+    use super::super::anf::{VariableNames, ParseAnfError, parse_anf};
+
+    use std::io;
+
+    #[test]
+    fn script_export() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        let polys = vec![parse_anf(f, "x1*x3 + x1 + 1", &names).unwrap(),
+                         parse_anf(f, "x1 + x4", &names).unwrap()];
+
+        let mut sage = Vec::new();
+        write_sage(f, &mut sage, &polys, &names, ScriptOrder::DegRevLex).unwrap();
+        assert_eq!(String::from_utf8(sage).unwrap(),
+                   "R.<x1,x3,x4> = BooleanPolynomialRing(order='degrevlex')\n\
+                    I = ideal([\n    x1*x3 + x1 + 1,\n    x1 + x4,\n])\n\
+                    print(I.groebner_basis())\n");
+
+        let mut names = VariableNames::new();
+        names.insert(4, "k");
+        let mut magma = Vec::new();
+        write_magma(f, &mut magma, &polys, &names, ScriptOrder::DegLex).unwrap();
+        assert_eq!(String::from_utf8(magma).unwrap(),
+                   "R<x1,x3,k> := BooleanPolynomialRing(3, \"glex\");\n\
+                    I := ideal<R | x1*x3 + x1 + 1, x1 + k>;\n\
+                    print GroebnerBasis(I);\n");
+
+        // Indexed names are no identifiers in either language.
+        let mut names = VariableNames::indexed();
+        names.insert(3, "key(0)");
+        names.insert(4, "k");
+        let mut sage = Vec::new();
+        write_sage(f, &mut sage, &polys, &names, ScriptOrder::Lex).unwrap();
+        assert_eq!(String::from_utf8(sage).unwrap(),
+                   "# x1 is x(1)\n# x3 is key(0)\n\
+                    R.<x1,x3,k> = BooleanPolynomialRing(order='lex')\n\
+                    I = ideal([\n    x1*x3 + x1 + 1,\n    x1 + k,\n])\n\
+                    print(I.groebner_basis())\n");
+        let mut magma = Vec::new();
+        write_magma(f, &mut magma, &polys, &names, ScriptOrder::Lex).unwrap();
+        assert!(String::from_utf8(magma).unwrap().starts_with("// x1 is x(1)\n// x3 is key(0)\nR<x1,x3,k> :="));
+
+        assert_eq!(write_sage(f, &mut Vec::new(), &[], &names, ScriptOrder::Lex).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+        assert_eq!(write_magma(f, &mut Vec::new(), &[], &names, ScriptOrder::Lex).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn script_parse_basis() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        let expected = vec![parse_anf(f, "x1*x3 + x1 + 1", &names).unwrap(),
+                            parse_anf(f, "x1 + x4", &names).unwrap()];
+
+        let sage = "Polynomial Sequence with 2 Polynomials in 3 Variables\n\n[x1*x3 + x1 + 1, x1 + x4]\n";
+        assert_eq!(parse_basis(f, sage, &names), Ok(expected.clone()));
+        let magma = "[\n    x1*x3 + x1 + 1,\n    (x1 + x4)\n]\n";
+        assert_eq!(parse_basis(f, magma, &names), Ok(expected.clone()));
+        assert_eq!(parse_basis(f, "[]", &names), Ok(vec![]));
+
+        assert_eq!(parse_basis(f, "[x1, x2 x3]", &names), Err(ParseAnfError::Unexpected(8)));
+        assert_eq!(parse_basis(f, "[x1, x2", &names), Err(ParseAnfError::End));
+
+        // The basis comes back in the generators the script was written
+        // with.
+        let mut names = VariableNames::indexed();
+        names.insert(3, "key(0)");
+        names.insert(4, "k");
+        let generators = write_sage(f, &mut Vec::new(), &expected, &names, ScriptOrder::Lex).unwrap();
+        let sage = "[x1*x3 + x1 + 1, x1 + k]";
+        assert_eq!(parse_basis(f, sage, &generators), Ok(expected));
+    }
+}
@@ -0,0 +1,470 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::anf::VariableNames;
+use super::word::Word;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone)]
+pub struct SmtOptions {
+    /// End with `(check-sat)`.
+    pub check_sat: bool,
+    /// End with `(get-model)`, after `check_sat`.
+    pub get_model: bool,
+    pub names: VariableNames,
+}
+
+impl SmtOptions {
+    pub fn new() -> SmtOptions {
+        SmtOptions {
+            check_sat: true,
+            get_model: true,
+            names: VariableNames::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SmtError {
+    Io(io::Error),
+    /// Model output that could not be understood, near this token.
+    Parse(String),
+}
+
+impl fmt::Display for SmtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SmtError::Io(ref err) => write!(f, "{}", err),
+            SmtError::Parse(ref token) => write!(f, "unexpected {:?} in model", token),
+        }
+    }
+}
+
+impl Error for SmtError {
+    fn description(&self) -> &str {
+        match *self {
+            SmtError::Io(ref err) => err.description(),
+            SmtError::Parse(_) => "malformed SMT model",
+        }
+    }
+}
+
+impl From<io::Error> for SmtError {
+    fn from(err: io::Error) -> SmtError {
+        SmtError::Io(err)
+    }
+}
+
+/// A symbol as SMT-LIB2 accepts it, quoted unless it is simple.
+fn symbol(name: &str) -> String {
+    let simple = !name.is_empty() && !name.starts_with(|c: char| c.is_digit(10)) &&
+                 name.chars().all(|c| c.is_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+    if simple { name.to_string() } else { format!("|{}|", name) }
+}
+
+/// Whether `name` can be a symbol of its own in a script: quoting cannot
+/// hold `|` or `\\`, and `|true|` is still `true`. Node definitions take the
+/// names `n<hex>`.
+fn representable(name: &str) -> bool {
+    let reserved = ["true", "false", "_", "!", "as", "let", "exists", "forall", "match", "par",
+                    "BINARY", "DECIMAL", "HEXADECIMAL", "NUMERAL", "STRING"];
+    let node = name.starts_with('n') && name.len() > 1 && name[1..].chars().all(|c| c.is_digit(16));
+    !name.is_empty() && !name.contains(|c: char| c == '|' || c == '\\') &&
+    !reserved.contains(&name) && !node
+}
+
+fn unquote(name: &str) -> &str {
+    if name.len() >= 2 && name.starts_with('|') && name.ends_with('|') { &name[1..name.len() - 1] } else { name }
+}
+
+/// Splits an s-expression into parentheses and atoms, keeping `|...|`
+/// symbols whole.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in text.chars() {
+        if quoted {
+            token.push(c);
+            quoted = c != '|';
+            continue;
+        }
+        match c {
+            '(' | ')' => {
+                if !token.is_empty() { tokens.push(token.clone()) }
+                token.clear();
+                tokens.push(c.to_string());
+            }
+            '|' => {
+                token.push(c);
+                quoted = true;
+            }
+            c if c.is_whitespace() => {
+                if !token.is_empty() { tokens.push(token.clone()) }
+                token.clear();
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() { tokens.push(token) }
+    tokens
+}
+
+/// An SMT-LIB2 script of equations `p = 0` and `Word` equalities.
+///
+/// Every forest node becomes one Boolean `define-fun`, as `x*hi xor lo`, so
+/// shared subgraphs are written once. Only variable words keep their
+/// bit-vector form: a word whose bits are exactly the variables of one
+/// declared with `declare_word` is written as that bit-vector, and a
+/// constant word as a literal. No word operations are recovered, so any
+/// other word is the concatenation of its bits' Boolean terms.
+///
+/// The extracts and concatenations this needs put a script with declared
+/// or asserted words in `QF_BV`; one without is `QF_UF`.
+#[derive(Debug, Clone)]
+pub struct SmtScript {
+    options: SmtOptions,
+    variables: BTreeSet<Variable>,
+    words: Vec<(String, Vec<Variable>)>,
+    defined: HashSet<NodeIdx>,
+    body: Vec<String>,
+    bit_vectors: bool,
+}
+
+impl SmtScript {
+    pub fn new(options: SmtOptions) -> SmtScript {
+        SmtScript {
+            options: options,
+            variables: BTreeSet::new(),
+            words: Vec::new(),
+            defined: HashSet::new(),
+            body: Vec::new(),
+            bit_vectors: false,
+        }
+    }
+
+    /// The symbol of `var`, which is `x<i>` if its name cannot be one.
+    fn name(&self, var: Variable) -> String {
+        let name = self.options.names.name(var);
+        if representable(&name) { symbol(&name) } else { format!("x{}", var) }
+    }
+
+    fn variable(&mut self, var: Variable) -> String {
+        self.variables.insert(var);
+        self.name(var)
+    }
+
+    /// The term for `idx`, defining its nodes first.
+    fn term(&mut self, f: &Forest, idx: NodeIdx) -> String {
+        match idx {
+            0 => return "false".to_string(),
+            1 => return "true".to_string(),
+            _ => {}
+        }
+        let name = format!("n{:x}", idx);
+        if self.defined.contains(&idx) {
+            return name;
+        }
+
+        let Node(var, hi, lo) = f.to_node(idx);
+        let x = self.variable(var);
+        if hi == 1 && lo == 0 {
+            return x;
+        }
+        let hi = self.term(f, hi);
+        let lo = self.term(f, lo);
+        let product = if hi == "true" { x } else { format!("(and {} {})", x, hi) };
+        let sum = match &lo[..] {
+            "false" => product,
+            "true" => format!("(not {})", product),
+            _ => format!("(xor {} {})", product, lo),
+        };
+
+        self.body.push(format!("(define-fun {} () Bool {})", name, sum));
+        self.defined.insert(idx);
+        name
+    }
+
+    fn word_term(&mut self, f: &Forest, word: &Word) -> String {
+        self.bit_vectors = true;
+        let bits: Vec<NodeIdx> = (0..32).map(|i| word.get_bit(i)).collect();
+        if bits.iter().all(|&bit| bit < 2) {
+            let value = bits.iter().rev().fold(0u32, |value, &bit| value << 1 | bit as u32);
+            return format!("#x{:08x}", value);
+        }
+
+        let vars: Vec<Option<Variable>> = bits.iter().map(|&bit| {
+            if bit < 2 { return None }
+            match f.to_node(bit) {
+                Node(var, 1, 0) => Some(var),
+                _ => None,
+            }
+        }).collect();
+        for &(ref name, ref word_vars) in &self.words {
+            if vars.iter().zip(word_vars.iter()).all(|(var, word_var)| *var == Some(*word_var)) {
+                return symbol(name);
+            }
+        }
+
+        let terms: Vec<String> = bits.iter().rev()
+            .map(|&bit| format!("(ite {} #b1 #b0)", self.term(f, bit)))
+            .collect();
+        terms[1..].iter().fold(terms[0].clone(), |word, bit| format!("(concat {} {})", word, bit))
+    }
+
+    /// Declares a 32-bit vector whose bits, least significant first, are
+    /// `bits`. Returns false and declares nothing if `name` cannot be a
+    /// symbol or is taken by a variable or another word.
+    pub fn declare_word(&mut self, name: &str, bits: &[Variable]) -> bool {
+        assert_eq!(bits.len(), 32);
+        if !representable(name) || self.options.names.variable(name).is_some() ||
+           self.words.iter().any(|&(ref word, _)| word == name) {
+            return false;
+        }
+        for &var in bits {
+            self.variables.insert(var);
+        }
+        self.words.push((name.to_string(), bits.to_vec()));
+        true
+    }
+
+    /// Adds the equation `p = 0`.
+    pub fn assert_zero(&mut self, f: &Forest, p: NodeIdx) {
+        let term = self.term(f, p);
+        self.body.push(format!("(assert (not {}))", term));
+    }
+
+    pub fn assert_words_equal(&mut self, f: &Forest, lhs: &Word, rhs: &Word) {
+        let lhs = self.word_term(f, lhs);
+        let rhs = self.word_term(f, rhs);
+        self.body.push(format!("(assert (= {} {}))", lhs, rhs));
+    }
+
+    pub fn assert_word_constant(&mut self, f: &Forest, lhs: &Word, value: u32) {
+        let lhs = self.word_term(f, lhs);
+        self.body.push(format!("(assert (= {} #x{:08x}))", lhs, value));
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let logic = if self.bit_vectors || !self.words.is_empty() { "QF_BV" } else { "QF_UF" };
+        try!(writeln!(writer, "(set-logic {})", logic));
+
+        let mut in_words: HashMap<Variable, (usize, usize)> = HashMap::new();
+        for (w, &(ref name, ref bits)) in self.words.iter().enumerate() {
+            try!(writeln!(writer, "(declare-const {} (_ BitVec 32))", symbol(name)));
+            for (i, &var) in bits.iter().enumerate() {
+                in_words.insert(var, (w, i));
+            }
+        }
+        for &var in &self.variables {
+            let name = self.name(var);
+            match in_words.get(&var) {
+                Some(&(w, i)) => try!(writeln!(writer, "(define-fun {} () Bool (= ((_ extract {} {}) {}) #b1))",
+                                               name, i, i, symbol(&self.words[w].0))),
+                None => try!(writeln!(writer, "(declare-const {} Bool)", name)),
+            }
+        }
+
+        for line in &self.body {
+            try!(writeln!(writer, "{}", line));
+        }
+        if self.options.check_sat {
+            try!(writeln!(writer, "(check-sat)"));
+            if self.options.get_model {
+                try!(writeln!(writer, "(get-model)"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a solver's answer to the script: `None` after `unsat`,
+    /// otherwise the true variables of the model. Both Boolean variables and
+    /// declared words are read.
+    pub fn decode_model<R: BufRead>(&self, reader: R) -> Result<Option<HashSet<Variable>>, SmtError> {
+        let mut text = String::new();
+        for line in reader.lines() {
+            text.push_str(&try!(line));
+            text.push('\n');
+        }
+        let tokens = tokenize(&text);
+
+        let mut solution = HashSet::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i][..] {
+                "unsat" => return Ok(None),
+                "define-fun" => {}
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+
+            let name = match tokens.get(i + 1) {
+                Some(name) => unquote(name).to_string(),
+                None => return Err(SmtError::Parse("define-fun".to_string())),
+            };
+            if tokens.get(i + 2).map(|t| &t[..]) != Some("(") || tokens.get(i + 3).map(|t| &t[..]) != Some(")") {
+                return Err(SmtError::Parse(name));
+            }
+            // The sort is `Bool` or `( _ BitVec 32 )`.
+            i += 4;
+            if tokens.get(i).map(|t| &t[..]) == Some("(") {
+                i += 5;
+            } else {
+                i += 1;
+            }
+
+            let value = match tokens.get(i) {
+                Some(value) => value.clone(),
+                None => return Err(SmtError::Parse(name)),
+            };
+            let value: u64 = match &value[..] {
+                "true" => 1,
+                "false" => 0,
+                _ if value.starts_with("#b") => try!(u64::from_str_radix(&value[2..], 2).map_err(|_| SmtError::Parse(value.clone()))),
+                _ if value.starts_with("#x") => try!(u64::from_str_radix(&value[2..], 16).map_err(|_| SmtError::Parse(value.clone()))),
+                // `(_ bv10 32)`
+                "(" if tokens.get(i + 1).map(|t| &t[..]) == Some("_") => {
+                    let bv = tokens.get(i + 2).cloned().unwrap_or(String::new());
+                    i += 4;
+                    if !bv.starts_with("bv") { return Err(SmtError::Parse(bv)) }
+                    try!(bv[2..].parse().map_err(|_| SmtError::Parse(bv.clone())))
+                }
+                _ => return Err(SmtError::Parse(value)),
+            };
+            i += 1;
+
+            if let Some(&(_, ref bits)) = self.words.iter().find(|&&(ref word, _)| *word == name) {
+                for (b, &var) in bits.iter().enumerate() {
+                    if value >> b & 1 == 1 { solution.insert(var); }
+                }
+            } else if let Some(var) = self.options.names.variable(&name) {
+                if value == 1 { solution.insert(var); }
+            }
+        }
+
+        Ok(Some(solution))
+    }
+}
+
+/// Writes the equations `p = 0` as an SMT-LIB2 script over Boolean
+/// variables.
+pub fn write_smt2<W: Write>(f: &Forest,
+                            writer: &mut W,
+                            polys: &[NodeIdx],
+                            options: &SmtOptions) -> io::Result<()> {
+    let mut script = SmtScript::new(options.clone());
+    for &p in polys {
+        script.assert_zero(f, p);
+    }
+    script.write(writer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, Variable};
+    use super::super::forest::Forest;
+    use super::super::anf::{VariableNames, parse_anf};
+    use super::super::word::Word;
+
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+
+    #[test]
+    fn smt_polynomials() {
+        let f = &mut Forest::new();
+        let names = VariableNames::new();
+        let p = parse_anf(f, "x0*x1 + x1 + 1", &names).unwrap();
+        let q = parse_anf(f, "x1 + 1", &names).unwrap();
+
+        let mut options = SmtOptions::new();
+        options.get_model = false;
+        let mut out = Vec::new();
+        write_smt2(f, &mut out, &[p, q], &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let x1_1 = format!("n{:x}", q);
+        assert_eq!(out, format!("(set-logic QF_UF)\n\
+                                 (declare-const x0 Bool)\n\
+                                 (declare-const x1 Bool)\n\
+                                 (define-fun {1} () Bool (not x1))\n\
+                                 (define-fun n{0:x} () Bool (xor (and x0 x1) {1}))\n\
+                                 (assert (not n{0:x}))\n\
+                                 (assert (not {1}))\n\
+                                 (check-sat)\n", p, x1_1));
+    }
+
+    #[test]
+    fn smt_words() {
+        let f = RefCell::new(Forest::new());
+        let x = Word::from_fn(&f, |i| f.borrow_mut().to_node_idx(Node(i as Variable, 1, 0)));
+        let y = Word::from_fn(&f, |i| f.borrow_mut().to_node_idx(Node(i as Variable + 32, 1, 0)));
+        let sum = &x ^ &y;
+
+        let mut options = SmtOptions::new();
+        options.names.insert(40, "y(8)");
+        let mut script = SmtScript::new(options);
+        script.declare_word("x", &(0..32).collect::<Vec<Variable>>());
+        script.assert_word_constant(&f.borrow(), &x, 10);
+        script.assert_words_equal(&f.borrow(), &sum, &Word::constant(&f, 3));
+        script.assert_zero(&f.borrow(), y.get_bit(8));
+
+        let mut out = Vec::new();
+        script.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("(set-logic QF_BV)\n(declare-const x (_ BitVec 32))\n"));
+        assert!(out.contains("(define-fun x3 () Bool (= ((_ extract 3 3) x) #b1))\n"));
+        assert!(out.contains("(declare-const |y(8)| Bool)\n"));
+        assert!(out.contains("(assert (= x #x0000000a))\n"));
+        assert!(out.contains("(assert (= (concat (concat "));
+        assert!(out.ends_with("(check-sat)\n(get-model)\n"));
+
+        let model = "sat\n(\n  (define-fun x () (_ BitVec 32)\n    #x0000000a)\n  \
+                     (define-fun x33 () Bool\n    true)\n  (define-fun |y(8)| () Bool false)\n  \
+                     (define-fun x34 () Bool true)\n)\n";
+        let expected: HashSet<Variable> = [1, 3, 33, 34].iter().cloned().collect();
+        assert_eq!(script.decode_model(model.as_bytes()).unwrap(), Some(expected));
+        let cvc = "sat\n(\n(define-fun x () (_ BitVec 32) (_ bv5 32))\n)\n";
+        let expected: HashSet<Variable> = [0, 2].iter().cloned().collect();
+        assert_eq!(script.decode_model(cvc.as_bytes()).unwrap(), Some(expected));
+        assert_eq!(script.decode_model("unsat\n".as_bytes()).unwrap(), None);
+    }
+
+    #[test]
+    fn smt_names() {
+        let f = &mut Forest::new();
+        let mut options = SmtOptions::new();
+        options.names.insert(0, "a|b");
+        options.names.insert(1, "true");
+        options.names.insert(2, "n1f");
+        options.names.insert(3, "c d");
+        let p = parse_anf(f, "x0*x1 + x2 + x3", &VariableNames::new()).unwrap();
+
+        // Names that cannot be symbols are written as their default names.
+        let mut script = SmtScript::new(options);
+        script.assert_zero(f, p);
+        let mut out = Vec::new();
+        script.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for name in &["x0", "x1", "x2", "|c d|"] {
+            assert!(out.contains(&format!("(declare-const {} Bool)\n", name)));
+        }
+        let model = "sat\n((define-fun x0 () Bool true) (define-fun x2 () Bool true) (define-fun |c d| () Bool true))\n";
+        let expected: HashSet<Variable> = [0, 2, 3].iter().cloned().collect();
+        assert_eq!(script.decode_model(model.as_bytes()).unwrap(), Some(expected));
+
+        let bits: Vec<Variable> = (32..64).collect();
+        assert!(!script.declare_word("false", &bits));
+        assert!(!script.declare_word("n2a", &bits));
+        assert!(!script.declare_word("c d", &bits));
+        assert!(!script.declare_word("x7", &bits));
+        assert!(script.declare_word("w", &bits));
+        assert!(!script.declare_word("w", &bits));
+    }
+}
@@ -0,0 +1,261 @@
+use super::node::{Node, NodeIdx, Variable};
+use super::forest::Forest;
+use super::add::add;
+use super::multiply::multiply;
+use super::ordering::{Lex, MonomialOrdering};
+use super::gf2::{to_matrix, from_matrix};
+use super::memoize::Memoize;
+use super::node_hasher::NodeHasherState;
+
+use std::collections::HashSet;
+
+type Memo = Memoize<NodeIdx, NodeIdx, NodeHasherState>;
+
+/// Replacements for any number of variables, applied simultaneously.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    map: Vec<Option<NodeIdx>>,
+}
+
+fn above(f: &Forest, idx: NodeIdx, var: Variable) -> bool {
+    idx < 2 || f.to_node(idx).0 > var
+}
+
+fn apply_map(f: &mut Forest, memo: &mut Memo, map: &[Option<NodeIdx>], idx: NodeIdx) -> NodeIdx {
+    if idx < 2 { return idx }
+    if let Some(result) = memo.get(&idx) { return result }
+
+    let Node(var, hi, lo) = f.to_node(idx);
+    let hi = apply_map(f, memo, map, hi);
+    let lo = apply_map(f, memo, map, lo);
+
+    // The node is rebuilt as is unless it is substituted, or a replacement
+    // below it brought in a smaller variable.
+    let result = match map[var as usize] {
+        None if above(f, hi, var) && above(f, lo, var) => f.to_node_idx(Node(var, hi, lo)),
+        replacement => {
+            let x = match replacement {
+                Some(poly) => poly,
+                None => f.to_node_idx(Node(var, 1, 0)),
+            };
+            let x_hi = multiply(f, x, hi);
+            add(f, x_hi, lo)
+        }
+    };
+
+    memo.set(idx, result)
+}
+
+impl Substitution {
+    pub fn new() -> Substitution {
+        Substitution { map: vec![None; 256] }
+    }
+
+    pub fn insert(&mut self, var: Variable, poly: NodeIdx) {
+        self.map[var as usize] = Some(poly);
+    }
+
+    pub fn get(&self, var: Variable) -> Option<NodeIdx> {
+        self.map[var as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.iter().all(|poly| poly.is_none())
+    }
+
+    /// Replaces every substituted variable of `idx` in a single pass over
+    /// the DAG. Replacements are not themselves substituted.
+    pub fn apply(&self, f: &mut Forest, idx: NodeIdx) -> NodeIdx {
+        apply_map(f, &mut Memoize::new(), &self.map, idx)
+    }
+
+    /// Like `apply` for several polynomials, sharing the work on common
+    /// subgraphs.
+    pub fn apply_all(&self, f: &mut Forest, polys: &[NodeIdx]) -> Vec<NodeIdx> {
+        let mut memo = Memoize::new();
+        polys.iter().map(|&p| apply_map(f, &mut memo, &self.map, p)).collect()
+    }
+}
+
+/// The variables eliminated from a system, in order, each with the
+/// polynomial it was replaced by. A replacement only mentions variables that
+/// remain or that were eliminated later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutionHistory {
+    steps: Vec<(Variable, NodeIdx)>,
+}
+
+impl SubstitutionHistory {
+    pub fn new() -> SubstitutionHistory {
+        SubstitutionHistory { steps: Vec::new() }
+    }
+
+    pub fn steps(&self) -> &[(Variable, NodeIdx)] {
+        &self.steps
+    }
+
+    pub fn push(&mut self, var: Variable, poly: NodeIdx) {
+        self.steps.push((var, poly));
+    }
+
+    /// Appends the eliminations made on the system `self` left behind.
+    pub fn extend(&mut self, other: &SubstitutionHistory) {
+        self.steps.extend(other.steps.iter().cloned());
+    }
+
+    /// Extends a solution of the reduced system, given as the set of true
+    /// variables, to the eliminated variables.
+    pub fn lift(&self, f: &Forest, solution: &HashSet<Variable>) -> HashSet<Variable> {
+        let mut lifted = solution.clone();
+        for &(var, poly) in self.steps.iter().rev() {
+            if f.evaluate(poly, &lifted) {
+                lifted.insert(var);
+            } else {
+                lifted.remove(&var);
+            }
+        }
+        lifted
+    }
+}
+
+/// Repeatedly solves the affine equations of the system for some of their
+/// variables and substitutes those into the remaining equations. Returns
+/// the equations left over, `[1]` if the affine ones contradict each other.
+pub fn eliminate_linear(f: &mut Forest, polys: &[NodeIdx]) -> (Vec<NodeIdx>, SubstitutionHistory) {
+    let mut history = SubstitutionHistory::new();
+    let mut system: Vec<NodeIdx> = polys.iter().cloned().filter(|&p| p != 0).collect();
+
+    loop {
+        let (linear, rest): (Vec<NodeIdx>, Vec<NodeIdx>) = system.into_iter()
+            .partition(|&p| f.degree(p) <= 1);
+        if linear.is_empty() {
+            return (rest, history);
+        }
+
+        // Solving the affine equations together resolves chains and cycles
+        // of definitions: each pivot ends up defined in terms of variables
+        // that are not pivots.
+        let (mut matrix, columns) = to_matrix(f, &linear, &Lex);
+        let rank = matrix.echelonize(true).len();
+        let rows: Vec<NodeIdx> = from_matrix(f, &matrix, &columns).into_iter().take(rank).collect();
+        if rows.contains(&1) {
+            return (vec![1], history);
+        }
+
+        let mut substitution = Substitution::new();
+        for row in rows {
+            let var = Lex.leading_monomial(f, row).unwrap().variables()[0];
+            let x = f.to_node_idx(Node(var, 1, 0));
+            let poly = add(f, row, x);
+            substitution.insert(var, poly);
+            history.push(var, poly);
+        }
+
+        system = substitution.apply_all(f, &rest);
+        system.retain(|&p| p != 0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::monomial::support;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn substitution_apply() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..4).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let x1x3 = multiply(f, x[1], x[3]);
+        let p = add(f, x0x1, x[1]);
+        let p = add(f, p, x1x3);
+
+        // x0 := x1 + x2 and x1 := x0 at the same time.
+        let mut substitution = Substitution::new();
+        let x1_x2 = add(f, x[1], x[2]);
+        substitution.insert(0, x1_x2);
+        substitution.insert(1, x[0]);
+        assert_eq!(substitution.get(1), Some(x[0]));
+
+        let x0x1 = multiply(f, x[0], x[1]);
+        let x0x2 = multiply(f, x[0], x[2]);
+        let x0x3 = multiply(f, x[0], x[3]);
+        let expected = add(f, x0x1, x0x2);
+        let expected = add(f, expected, x[0]);
+        let expected = add(f, expected, x0x3);
+        assert_eq!(substitution.apply(f, p), expected);
+        assert_eq!(substitution.apply_all(f, &[p, x[3], 1]), vec![expected, x[3], 1]);
+
+        // Only x3 moves, above the untouched x1.
+        let mut substitution = Substitution::new();
+        substitution.insert(3, x[0]);
+        let x0x1 = multiply(f, x[0], x[1]);
+        let expected = add(f, x[1], x0x1);
+        let p = add(f, x[1], x1x3);
+        assert_eq!(substitution.apply(f, p), expected);
+        assert!(!substitution.is_empty());
+        assert!(Substitution::new().is_empty());
+    }
+
+    #[test]
+    fn substitution_eliminate_linear() {
+        let mut lfsr: u32 = 17;
+
+        for round in 0..12 {
+            let f = &mut Forest::new();
+            let mut polys: Vec<NodeIdx> = Vec::new();
+            for i in 0..8 {
+                let mut p = 0;
+                for _ in 0..3 {
+                    lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                    let a = f.to_node_idx(Node((lfsr >> 16) as Variable % 8, 1, 0));
+                    let b = f.to_node_idx(Node((lfsr >> 24) as Variable % 8, 1, 0));
+                    let term = if i < 4 { a } else { multiply(f, a, b) };
+                    p = add(f, p, term);
+                }
+                if lfsr & 0x100 != 0 { p = add(f, p, 1) }
+                polys.push(p);
+            }
+            if round == 0 {
+                // A cycle of definitions: x0 = x1 + 1, x1 = x2, x2 = x0 + 1.
+                let x: Vec<NodeIdx> = (0..3).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+                let p = add(f, x[0], x[1]);
+                polys.push(add(f, p, 1));
+                polys.push(add(f, x[1], x[2]));
+                let p = add(f, x[2], x[0]);
+                polys.push(add(f, p, 1));
+            }
+
+            let (system, history) = eliminate_linear(f, &polys);
+            let eliminated: HashSet<Variable> = history.steps().iter().map(|&(var, _)| var).collect();
+            assert_eq!(eliminated.len(), history.steps().len());
+            for &p in &system {
+                assert!(system == [1] || f.degree(p) > 1);
+                assert!(support(f, p).all(|var| !eliminated.contains(&var)));
+            }
+
+            let mut expected = Vec::new();
+            let mut lifted = Vec::new();
+            for mask in 0..256u32 {
+                let solution: HashSet<Variable> = (0..8).filter(|&i| mask >> i & 1 == 1).collect();
+                if polys.iter().all(|&p| !f.evaluate(p, &solution)) {
+                    expected.push(mask);
+                }
+                if eliminated.iter().any(|var| solution.contains(var)) { continue }
+                if system.iter().all(|&p| !f.evaluate(p, &solution)) {
+                    let solution = history.lift(f, &solution);
+                    lifted.push((0..8).filter(|i| solution.contains(i)).fold(0, |m, i| m | 1 << i));
+                }
+            }
+            lifted.sort();
+            assert_eq!(lifted, expected);
+        }
+    }
+}
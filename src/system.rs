@@ -0,0 +1,420 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial_count::monomial_count;
+use super::monomial::support;
+use super::word::Word;
+use super::substitution::{self, SubstitutionHistory};
+use super::cnf::{self, Cnf, CnfOptions, DimacsOptions, DimacsError};
+
+use std::cell::RefCell;
+use std::io::BufRead;
+use std::collections::HashSet;
+
+/// A set of equations `p = 0`, together with the forest that holds them.
+#[derive(Debug)]
+pub struct PolynomialSystem {
+    forest: Forest,
+    polynomials: Vec<NodeIdx>,
+}
+
+/// Equations of a system that share no variables with the rest of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub polynomials: Vec<NodeIdx>,
+    pub variables: Vec<Variable>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemStats {
+    pub equations: usize,
+    pub variables: usize,
+    /// `degrees[d]` equations have degree `d`.
+    pub degrees: Vec<usize>,
+    /// Summed over all equations.
+    pub monomials: usize,
+    pub max_monomials: usize,
+}
+
+impl PolynomialSystem {
+    pub fn new(forest: Forest) -> PolynomialSystem {
+        PolynomialSystem::with_polynomials(forest, Vec::new())
+    }
+
+    pub fn with_polynomials(forest: Forest, polynomials: Vec<NodeIdx>) -> PolynomialSystem {
+        PolynomialSystem {
+            forest: forest,
+            polynomials: polynomials,
+        }
+    }
+
+    /// Builds a system from constraints on words over a forest that is only
+    /// borrowed while `build` runs.
+    pub fn from_words<F>(forest: Forest, build: F) -> PolynomialSystem
+        where F: for<'a> FnOnce(&mut WordConstraints<'a>)
+    {
+        let forest = RefCell::new(forest);
+        let polynomials = {
+            let mut constraints = WordConstraints {
+                forest: &forest,
+                polynomials: Vec::new(),
+            };
+            build(&mut constraints);
+            constraints.polynomials
+        };
+
+        PolynomialSystem::with_polynomials(forest.into_inner(), polynomials)
+    }
+
+    /// Reads a DIMACS CNF file, see `read_dimacs`.
+    pub fn from_dimacs<R: BufRead>(reader: R, options: &DimacsOptions) -> Result<PolynomialSystem, DimacsError> {
+        let mut forest = Forest::new();
+        let polynomials = try!(cnf::read_dimacs(&mut forest, reader, options));
+        Ok(PolynomialSystem::with_polynomials(forest, polynomials))
+    }
+
+    pub fn forest(&self) -> &Forest {
+        &self.forest
+    }
+
+    pub fn forest_mut(&mut self) -> &mut Forest {
+        &mut self.forest
+    }
+
+    pub fn polynomials(&self) -> &[NodeIdx] {
+        &self.polynomials
+    }
+
+    /// The forest and the equations at once, for algorithms that build new
+    /// polynomials from the system's.
+    pub fn parts_mut(&mut self) -> (&mut Forest, &mut Vec<NodeIdx>) {
+        (&mut self.forest, &mut self.polynomials)
+    }
+
+    pub fn into_parts(self) -> (Forest, Vec<NodeIdx>) {
+        (self.forest, self.polynomials)
+    }
+
+    pub fn push(&mut self, p: NodeIdx) {
+        self.polynomials.push(p);
+    }
+
+    pub fn len(&self) -> usize {
+        self.polynomials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polynomials.is_empty()
+    }
+
+    /// Removes repeated equations, keeping the first of each.
+    pub fn dedup(&mut self) {
+        let mut seen = HashSet::new();
+        self.polynomials.retain(|&p| seen.insert(p));
+    }
+
+    /// Drops the trivial equations `0 = 0`. If `1 = 0` is among the
+    /// equations the system is replaced by it alone.
+    pub fn remove_trivial(&mut self) {
+        if self.is_inconsistent() {
+            self.polynomials = vec![1];
+        } else {
+            self.polynomials.retain(|&p| p != 0);
+        }
+    }
+
+    pub fn simplify(&mut self) {
+        self.remove_trivial();
+        self.dedup();
+    }
+
+    pub fn is_inconsistent(&self) -> bool {
+        self.polynomials.contains(&1)
+    }
+
+    /// Eliminates variables through the affine equations, see
+    /// `eliminate_linear`. The history lifts solutions of what remains back
+    /// to the original variables.
+    pub fn eliminate_linear(&mut self) -> SubstitutionHistory {
+        let (polynomials, history) = substitution::eliminate_linear(&mut self.forest,
+                                                                    &self.polynomials);
+        self.polynomials = polynomials;
+        history
+    }
+
+    /// The equations as clauses for a SAT solver, see `Cnf`.
+    pub fn to_cnf(&self, options: &CnfOptions) -> Cnf {
+        Cnf::from_polynomials(&self.forest, &self.polynomials, options)
+    }
+
+    /// The variables the equations depend on, in ascending order.
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut vars: Vec<Variable> = Vec::new();
+        for &p in &self.polynomials {
+            vars.extend(support(&self.forest, p));
+        }
+        vars.sort();
+        vars.dedup();
+        vars
+    }
+
+    pub fn stats(&self) -> SystemStats {
+        let mut stats = SystemStats {
+            equations: self.polynomials.len(),
+            variables: self.variables().len(),
+            degrees: Vec::new(),
+            monomials: 0,
+            max_monomials: 0,
+        };
+
+        for &p in &self.polynomials {
+            let degree = self.forest.degree(p);
+            if stats.degrees.len() <= degree {
+                stats.degrees.resize(degree + 1, 0);
+            }
+            stats.degrees[degree] += 1;
+
+            let count = monomial_count(&self.forest, p);
+            stats.monomials += count;
+            stats.max_monomials = stats.max_monomials.max(count);
+        }
+
+        stats
+    }
+
+    /// Splits the equations into groups with disjoint variables, ordered by
+    /// their smallest variable. Constant equations form a component without
+    /// variables at the front.
+    pub fn components(&self) -> Vec<Component> {
+        let supports: Vec<Vec<Variable>> = self.polynomials
+            .iter()
+            .map(|&p| support(&self.forest, p).collect())
+            .collect();
+
+        // Union-find over the variables, joining those that share an
+        // equation. Each root is the smallest variable of its set.
+        let mut parent: Vec<usize> = (0..256).collect();
+        for vars in &supports {
+            for &var in vars.iter().skip(1) {
+                let (a, b) = (find(&mut parent, vars[0] as usize), find(&mut parent, var as usize));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+
+        // Slot 256 collects the constant equations.
+        let mut components: Vec<Component> = (0..257)
+            .map(|_| Component { polynomials: Vec::new(), variables: Vec::new() })
+            .collect();
+        for (&p, vars) in self.polynomials.iter().zip(supports.iter()) {
+            let slot = match vars.first() {
+                Some(&var) => find(&mut parent, var as usize),
+                None => 256,
+            };
+            components[slot].polynomials.push(p);
+            components[slot].variables.extend(vars.iter().cloned());
+        }
+
+        let constant = components.pop().unwrap();
+        let mut result: Vec<Component> = Vec::new();
+        if !constant.polynomials.is_empty() {
+            result.push(constant);
+        }
+        for mut component in components {
+            if component.polynomials.is_empty() { continue }
+            component.variables.sort();
+            component.variables.dedup();
+            result.push(component);
+        }
+        result
+    }
+
+    /// The indices of the equations that `solution`, the set of true
+    /// variables, violates.
+    pub fn verify(&self, solution: &HashSet<Variable>) -> Vec<usize> {
+        self.polynomials
+            .iter()
+            .enumerate()
+            .filter(|&(_, &p)| self.forest.evaluate(p, solution))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn find(parent: &mut Vec<usize>, var: usize) -> usize {
+    let mut root = var;
+    while parent[root] != root { root = parent[root] }
+
+    let mut var = var;
+    while parent[var] != root {
+        let next = parent[var];
+        parent[var] = root;
+        var = next;
+    }
+    root
+}
+
+/// Combines solutions of independent components, each given as the set of
+/// true variables, into every solution of the whole system.
+pub fn combine_solutions(parts: &[Vec<HashSet<Variable>>]) -> Vec<HashSet<Variable>> {
+    parts.iter().fold(vec![HashSet::new()], |partial, solutions| {
+        let mut combined = Vec::with_capacity(partial.len() * solutions.len());
+        for prefix in &partial {
+            for solution in solutions {
+                combined.push(prefix.union(solution).cloned().collect());
+            }
+        }
+        combined
+    })
+}
+
+/// Collects equations between words for `PolynomialSystem::from_words`.
+pub struct WordConstraints<'a> {
+    forest: &'a RefCell<Forest>,
+    polynomials: Vec<NodeIdx>,
+}
+
+impl<'a> WordConstraints<'a> {
+    /// The forest to build words in.
+    pub fn forest(&self) -> &'a RefCell<Forest> {
+        self.forest
+    }
+
+    /// `p = 0`.
+    pub fn zero(&mut self, p: NodeIdx) {
+        self.polynomials.push(p);
+    }
+
+    /// `lhs = rhs`, one equation per bit.
+    pub fn equal(&mut self, lhs: &Word<'a>, rhs: &Word<'a>) {
+        let sum = lhs ^ rhs;
+        self.polynomials.extend((0..32).map(|i| sum.get_bit(i)));
+    }
+
+    pub fn equal_constant(&mut self, lhs: &Word<'a>, value: u32) {
+        let rhs = Word::constant(self.forest, value);
+        self.equal(lhs, &rhs);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+    use super::super::word::Word;
+
+    use std::collections::HashSet;
+
+    fn assignment(x: u32, y: u32) -> HashSet<Variable> {
+        (0..4).filter(|&i| x >> i & 1 == 1)
+              .chain((0..4).filter(|&i| y >> i & 1 == 1).map(|i| i + 4))
+              .collect()
+    }
+
+    #[test]
+    fn system_from_words() {
+        let mut system = PolynomialSystem::from_words(Forest::new(), |constraints| {
+            let f = constraints.forest();
+            let x = Word::from_fn(f, |i| {
+                if i < 4 { f.borrow_mut().to_node_idx(Node(i as Variable, 1, 0)) } else { 0 }
+            });
+            let y = Word::from_fn(f, |i| {
+                if i < 4 { f.borrow_mut().to_node_idx(Node(i as Variable + 4, 1, 0)) } else { 0 }
+            });
+
+            constraints.equal_constant(&(&x + &y), 9);
+            constraints.equal_constant(&(&x ^ &y), 1);
+        });
+
+        assert_eq!(system.len(), 64);
+        assert!(!system.is_inconsistent());
+        assert_eq!(system.verify(&assignment(4, 5)), vec![]);
+        assert_eq!(system.verify(&assignment(5, 4)), vec![]);
+        // 3 ^ 6 = 5 breaks bit 2 of the second constraint.
+        assert_eq!(system.verify(&assignment(3, 6)), vec![34]);
+
+        system.simplify();
+        assert_eq!(system.variables(), (0..8).collect::<Vec<Variable>>());
+        assert!(system.len() < 64);
+        assert_eq!(system.verify(&assignment(4, 5)), vec![]);
+        assert!(!system.verify(&assignment(3, 6)).is_empty());
+
+        let stats = system.stats();
+        assert_eq!(stats.equations, system.len());
+        assert_eq!(stats.variables, 8);
+        assert_eq!(stats.degrees.iter().fold(0, |sum, &n| sum + n), system.len());
+        assert_eq!(stats.degrees[0], 0);
+        assert!(stats.max_monomials <= stats.monomials);
+    }
+
+    #[test]
+    fn system_components() {
+        let mut f = Forest::new();
+        let x: Vec<NodeIdx> = (0..8).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x5 = multiply(&mut f, x[0], x[5]);
+        let p = add(&mut f, x0x5, x[2]);
+        let q = add(&mut f, x[3], x[4]);
+        let r = add(&mut f, x[2], 1);
+        let s = add(&mut f, x[4], x[6]);
+        let system = PolynomialSystem::with_polynomials(f, vec![p, q, 1, r, s, x[1]]);
+
+        let components = system.components();
+        assert_eq!(components, vec![
+            Component { polynomials: vec![1], variables: vec![] },
+            Component { polynomials: vec![p, r], variables: vec![0, 2, 5] },
+            Component { polynomials: vec![x[1]], variables: vec![1] },
+            Component { polynomials: vec![q, s], variables: vec![3, 4, 6] },
+        ]);
+
+        // x0 = x5 = x2 = 1, x1 = 0, x3 = x4 = x6 in either way.
+        let parts = vec![
+            vec![[0, 2, 5].iter().cloned().collect()],
+            vec![HashSet::new()],
+            vec![HashSet::new(), [3, 4, 6].iter().cloned().collect()],
+        ];
+        let solutions = combine_solutions(&parts);
+        assert_eq!(solutions.len(), 2);
+        for solution in &solutions {
+            assert_eq!(system.verify(solution), vec![2]);
+        }
+        assert_eq!(combine_solutions(&[]), vec![HashSet::new()]);
+    }
+
+    #[test]
+    fn system_trivial() {
+        let mut f = Forest::new();
+        let x0 = f.to_node_idx(Node(0, 1, 0));
+        let mut system = PolynomialSystem::with_polynomials(f, vec![0, x0, 0, x0]);
+
+        system.simplify();
+        assert_eq!(system.polynomials(), &[x0]);
+
+        system.push(1);
+        assert!(system.is_inconsistent());
+        system.simplify();
+        assert_eq!(system.polynomials(), &[1]);
+        assert_eq!(system.stats().degrees, vec![1]);
+    }
+
+    #[test]
+    fn system_eliminate_linear() {
+        let mut f = Forest::new();
+        let x: Vec<NodeIdx> = (0..3).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let p = add(&mut f, x[0], x[1]);
+        let p = add(&mut f, p, 1);
+        let x0x2 = multiply(&mut f, x[0], x[2]);
+        let q = add(&mut f, x0x2, x[1]);
+        let mut system = PolynomialSystem::with_polynomials(f, vec![p, q]);
+
+        // x0 = x1 + 1 leaves x1*x2 + x2 + x1.
+        let history = system.eliminate_linear();
+        assert_eq!(history.steps().len(), 1);
+        assert_eq!(system.variables(), vec![1, 2]);
+
+        let solution: HashSet<Variable> = HashSet::new();
+        assert_eq!(system.verify(&solution), vec![]);
+        let lifted = history.lift(system.forest(), &solution);
+        assert_eq!(lifted, [0].iter().cloned().collect());
+    }
+}
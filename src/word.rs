@@ -2,9 +2,15 @@ use super::node::{NodeIdx, Variable};
 use super::forest::Forest;
 use super::add::add;
 use super::multiply::multiply;
+use super::ordering::MonomialOrdering;
+use super::normal_form::{Reduction, normal_form};
+use super::anf::{VariableNames, anf};
+use super::dot::{DotOptions, write_dot};
 
 use std::collections::HashSet;
 use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
 use std::hash::{Hash, Hasher};
 
 use std::ops::{Add, BitAnd, BitXor, Not, Shr};
@@ -72,6 +78,38 @@ impl<'a> Word<'a> {
         self.bits[bit]
     }
 
+    /// Fully reduces every bit modulo `basis`.
+    pub fn normal_form<O>(&self, basis: &[NodeIdx], ordering: &O) -> Word<'a>
+        where O: MonomialOrdering
+    {
+        let mut word = Word::new(self.forest);
+        let mut f = self.forest.borrow_mut();
+
+        for i in 0..32 {
+            word.bits[i] = normal_form(&mut f, self.bits[i], basis, ordering, Reduction::Full);
+        }
+
+        word
+    }
+
+    /// Every bit as text, least significant first.
+    pub fn format_bits<O>(&self, names: &VariableNames, ordering: &O) -> Vec<String>
+        where O: MonomialOrdering
+    {
+        let f = self.forest.borrow();
+        self.bits
+            .iter()
+            .map(|&bit| anf(&f, bit).with_names(names).with_ordering(ordering).to_string())
+            .collect()
+    }
+
+    /// Draws all 32 bits as one DOT graph, entered at `bit0` to `bit31`.
+    pub fn write_dot<W: Write>(&self, writer: &mut W, options: &DotOptions) -> io::Result<()> {
+        let labels: Vec<String> = (0..32).map(|i| format!("bit{}", i)).collect();
+        let roots: Vec<(&str, NodeIdx)> = labels.iter().map(|label| &label[..]).zip(self.bits.iter().cloned()).collect();
+        write_dot(&self.forest.borrow(), writer, &roots, options)
+    }
+
     pub fn evaluate<'b, 'c>(&self, variable_map: &'b HashSet<Variable>) -> u32 {
         self.bits
             .iter()
@@ -87,6 +125,18 @@ impl<'a> Word<'a> {
     }
 }
 
+impl<'a> fmt::Display for Word<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let forest = self.forest.borrow();
+        try!(write!(f, "["));
+        for i in 0..32 {
+            if i > 0 { try!(write!(f, ", ")) }
+            try!(write!(f, "{}", anf(&forest, self.bits[i])));
+        }
+        write!(f, "]")
+    }
+}
+
 impl<'a, 'b, 'c> Add<&'c Word<'a>> for &'b Word<'a> {
     type Output = Word<'a>;
 
@@ -194,6 +244,8 @@ mod test {
     use super::*;
     use super::super::node::{Node, Variable};
     use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::ordering::Lex;
 
     use std::collections::HashSet;
     use std::cell::RefCell;
@@ -248,4 +300,52 @@ mod test {
         assert_eq!(z.evaluate(&HashSet::new()), 0x147);
         assert_eq!(w.evaluate(&HashSet::new()), 0xC0DEC0DE);
     }
+
+    #[test]
+    fn word_normal_form() {
+        let f = RefCell::new(Forest::new());
+
+        let x = Word::from_fn(&f, |i| {
+            f.borrow_mut().to_node_idx(Node((i % 4) as Variable, 1, 0))
+        });
+        let y = Word::constant(&f, 0x0F0F0F0F);
+
+        // x0 = 1, x1 = x2, x3 = 0
+        let basis = {
+            let mut f = f.borrow_mut();
+            let x0 = f.to_node_idx(Node(0, 1, 0));
+            let x1 = f.to_node_idx(Node(1, 1, 0));
+            let x2 = f.to_node_idx(Node(2, 1, 0));
+            let x3 = f.to_node_idx(Node(3, 1, 0));
+            vec![add(&mut f, x0, 1), add(&mut f, x1, x2), x3]
+        };
+
+        let z = (&x + &y).normal_form(&basis, &Lex);
+        let none_set: HashSet<Variable> = HashSet::new();
+        let all_set: HashSet<Variable> = (0..4).collect();
+        for set in &[none_set, all_set] {
+            let x2 = if set.contains(&2) { 1 } else { 0 };
+            let expected = Word::from_fn(&f, |i| [1, x2, x2, 0][i % 4]);
+            assert_eq!(z.evaluate(set), (&expected + &y).evaluate(set));
+        }
+    }
+
+    #[test]
+    fn word_display() {
+        let f = RefCell::new(Forest::new());
+        let x = Word::from_fn(&f, |i| {
+            if i < 2 { f.borrow_mut().to_node_idx(Node(i as Variable, 1, 0)) } else { 0 }
+        });
+        let y = &x ^ &Word::constant(&f, 1);
+
+        assert!(y.to_string().starts_with("[x0 + 1, x1, 0, "));
+        let mut names = VariableNames::new();
+        names.insert(1, "carry");
+        assert_eq!(&y.format_bits(&names, &Lex)[..3], &["x0 + 1", "carry", "0"]);
+
+        let mut out = Vec::new();
+        y.write_dot(&mut out, &DotOptions::new()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("root31 [label=\"bit31\", shape=plaintext];\nroot31 -> zero;"));
+    }
 }
@@ -0,0 +1,346 @@
+use super::node::{NodeIdx, Variable};
+use super::forest::Forest;
+use super::monomial::{Monomial, monomials, support};
+use super::ordering::DegLex;
+use super::divide::multiply_by_monomial;
+use super::gf2::{to_matrix, from_matrix};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct XlOptions {
+    /// The degree to expand the system to.
+    pub degree: usize,
+    /// Multiply only by monomials in these variables instead of every
+    /// variable of the system.
+    pub variables: Option<Vec<Variable>>,
+    /// Give up rather than build a matrix larger than this many bytes.
+    pub memory_budget: Option<usize>,
+    /// Mutant-XL: equations that drop below the current degree after
+    /// elimination are multiplied out again before the degree is raised.
+    pub mutant: bool,
+}
+
+impl XlOptions {
+    pub fn new(degree: usize) -> XlOptions {
+        XlOptions {
+            degree: degree,
+            variables: None,
+            memory_budget: None,
+            mutant: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XlError {
+    /// The matrix at `degree` needed `bytes` bytes for its first `rows`
+    /// rows already.
+    MemoryBudget { degree: usize, rows: usize, columns: usize, bytes: usize },
+}
+
+impl fmt::Display for XlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XlError::MemoryBudget { degree, rows, columns, bytes } => {
+                write!(f, "degree {} matrix of {}x{} needs {} bytes", degree, rows, columns, bytes)
+            }
+        }
+    }
+}
+
+impl Error for XlError {
+    fn description(&self) -> &str {
+        match *self {
+            XlError::MemoryBudget { .. } => "XL matrix exceeds the memory budget",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct XlOutput {
+    /// The degree the system was last expanded to.
+    pub degree: usize,
+    /// Independent equations of degree at most one, in reduced echelon form.
+    /// `[1]` if the system has no solution.
+    pub linear: Vec<NodeIdx>,
+    /// Variables fixed by a univariate equation, in ascending order.
+    pub assignments: Vec<(Variable, bool)>,
+}
+
+impl XlOutput {
+    pub fn is_inconsistent(&self) -> bool {
+        self.linear == [1]
+    }
+}
+
+/// Every monomial in `vars` of degree at most `degree`, by degree and then
+/// in lexicographic order of the positions in `vars`. They are generated one
+/// at a time, as there can be far more of them than fit the budget.
+struct Multipliers<'a> {
+    vars: &'a [Variable],
+    degree: usize,
+    positions: Option<Vec<usize>>,
+}
+
+fn multipliers(vars: &[Variable], degree: usize) -> Multipliers {
+    Multipliers { vars: vars, degree: degree, positions: Some(Vec::new()) }
+}
+
+impl<'a> Iterator for Multipliers<'a> {
+    type Item = Monomial;
+
+    fn next(&mut self) -> Option<Monomial> {
+        let m = match self.positions {
+            Some(ref positions) => {
+                let vars: Vec<Variable> = positions.iter().map(|&i| self.vars[i]).collect();
+                Monomial::from_vars(&vars)
+            }
+            None => return None,
+        };
+
+        let n = self.vars.len();
+        let mut positions = self.positions.take().unwrap();
+        let k = positions.len();
+        // The last position that can still move right, then the ones after
+        // it packed behind it.
+        match (0..k).rev().find(|&j| positions[j] < n - k + j) {
+            Some(j) => {
+                positions[j] += 1;
+                for i in j + 1..k {
+                    positions[i] = positions[i - 1] + 1;
+                }
+                self.positions = Some(positions);
+            }
+            None if k < self.degree && k < n => self.positions = Some((0..k + 1).collect()),
+            None => {}
+        }
+        Some(m)
+    }
+}
+
+/// The products `m * p` of degree at most `degree`, without duplicates.
+/// With a budget the matrix size is tracked row by row, and multipliers are
+/// generated as they are used, so that expansion stops as soon as the budget
+/// is exceeded rather than after every product has been built.
+fn expand(f: &mut Forest,
+          polys: &[NodeIdx],
+          vars: &[Variable],
+          degree: usize,
+          budget: Option<usize>) -> Result<Vec<NodeIdx>, XlError> {
+    let mut seen = HashSet::new();
+    let mut columns = HashSet::new();
+    let mut rows = Vec::new();
+
+    for &p in polys {
+        let room = degree.saturating_sub(f.degree(p));
+        for m in multipliers(vars, room) {
+            let row = multiply_by_monomial(f, p, &m);
+            if row == 0 || !seen.insert(row) { continue }
+            rows.push(row);
+
+            if let Some(budget) = budget {
+                columns.extend(monomials(f, row));
+                let bytes = rows.len() * ((columns.len() + 63) / 64) * 8;
+                if bytes > budget {
+                    return Err(XlError::MemoryBudget {
+                        degree: degree,
+                        rows: rows.len(),
+                        columns: columns.len(),
+                        bytes: bytes,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Solves a polynomial system by extended linearization: every equation is
+/// multiplied by all monomials up to `options.degree`, and the products are
+/// eliminated as a linear system over their monomials, graded so that
+/// linear equations come out last.
+///
+/// With `options.mutant` every degree from that of the system up is tried in
+/// turn, stopping early once each variable is fixed.
+pub fn xl(f: &mut Forest, polys: &[NodeIdx], options: &XlOptions) -> Result<XlOutput, XlError> {
+    let mut vars: Vec<Variable> = Vec::new();
+    for &p in polys {
+        vars.extend(support(f, p));
+    }
+    vars.sort();
+    vars.dedup();
+
+    let multiplier_vars = options.variables.clone().unwrap_or(vars.clone());
+    let start = polys.iter().map(|&p| f.degree(p)).max().unwrap_or(0);
+    let first = if options.mutant { start } else { options.degree.max(start) };
+
+    let mut base: Vec<NodeIdx> = polys.iter().cloned().filter(|&p| p != 0).collect();
+    let mut output = XlOutput { degree: first, linear: Vec::new(), assignments: Vec::new() };
+
+    for degree in first..options.degree.max(start) + 1 {
+        let mut last_rank = 0;
+
+        loop {
+            let rows = try!(expand(f, &base, &multiplier_vars, degree, options.memory_budget));
+
+            let (mut matrix, columns) = to_matrix(f, &rows, &DegLex);
+            let rank = matrix.echelonize(true).len();
+            let reduced: Vec<NodeIdx> = from_matrix(f, &matrix, &columns)
+                .into_iter()
+                .take(rank)
+                .collect();
+
+            output.degree = degree;
+            output.linear = reduced.iter().cloned().filter(|&row| f.degree(row) <= 1).collect();
+            if output.linear.contains(&1) {
+                output.linear = vec![1];
+                return Ok(output);
+            }
+            if !options.mutant || rank <= last_rank { break }
+            last_rank = rank;
+
+            let known: HashSet<NodeIdx> = base.iter().cloned().collect();
+            let mutants: Vec<NodeIdx> = reduced.into_iter()
+                .filter(|&row| f.degree(row) < degree && !known.contains(&row))
+                .collect();
+            if mutants.is_empty() { break }
+            base.extend(mutants);
+        }
+
+        if output.linear.len() == vars.len() { break }
+    }
+
+    output.assignments = output.linear
+        .iter()
+        .filter_map(|&row| {
+            let terms: Vec<Monomial> = monomials(f, row).collect();
+            match terms.len() {
+                1 => Some((terms[0].variables()[0], false)),
+                2 if terms[1] == Monomial::one() => Some((terms[0].variables()[0], true)),
+                _ => None,
+            }
+        })
+        .collect();
+    output.assignments.sort();
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::node::{Node, NodeIdx, Variable};
+    use super::super::forest::Forest;
+    use super::super::add::add;
+    use super::super::multiply::multiply;
+
+    use std::collections::HashSet;
+
+    fn random_system(f: &mut Forest, lfsr: &mut u32, count: usize, solution: &HashSet<Variable>) -> Vec<NodeIdx> {
+        (0..count).map(|_| {
+            let mut p = 0;
+            for _ in 0..6 {
+                *lfsr = lfsr.wrapping_mul(1103515245).wrapping_add(12345);
+                let a = f.to_node_idx(Node((*lfsr >> 16) as Variable % 6, 1, 0));
+                let b = f.to_node_idx(Node((*lfsr >> 24) as Variable % 6, 1, 0));
+                let ab = multiply(f, a, b);
+                p = add(f, p, ab);
+            }
+            if f.evaluate(p, solution) { add(f, p, 1) } else { p }
+        }).collect()
+    }
+
+    #[test]
+    fn xl_solves() {
+        let f = &mut Forest::new();
+        let mut lfsr = 5;
+        let solution: HashSet<Variable> = [0, 2, 3].iter().cloned().collect();
+        let polys = random_system(f, &mut lfsr, 14, &solution);
+
+        let plain = xl(f, &polys, &XlOptions::new(3)).unwrap();
+        assert_eq!(plain.degree, 3);
+
+        let mut options = XlOptions::new(3);
+        options.mutant = true;
+        let mutant = xl(f, &polys, &options).unwrap();
+        assert!(mutant.degree <= plain.degree);
+
+        for output in &[plain, mutant] {
+            assert!(!output.is_inconsistent());
+            assert!(!output.assignments.is_empty());
+            for &(var, value) in &output.assignments {
+                assert_eq!(solution.contains(&var), value);
+            }
+            for &row in &output.linear {
+                assert!(!f.evaluate(row, &solution));
+            }
+        }
+    }
+
+    #[test]
+    fn xl_options() {
+        let f = &mut Forest::new();
+
+        let x: Vec<NodeIdx> = (0..3).map(|i| f.to_node_idx(Node(i, 1, 0))).collect();
+        let x0x1 = multiply(f, x[0], x[1]);
+        let p = add(f, x0x1, x[2]);
+        let q = add(f, x0x1, 1);
+        let r = add(f, x[1], x[2]);
+
+        // x0*x1 = 1 forces x0 = x1 = 1 after multiplying by x0 and x1.
+        let output = xl(f, &[q], &XlOptions::new(3)).unwrap();
+        assert_eq!(output.assignments, vec![(0, true), (1, true)]);
+
+        let mut options = XlOptions::new(3);
+        options.variables = Some(vec![0]);
+        let output = xl(f, &[q], &options).unwrap();
+        assert_eq!(output.assignments, vec![(0, true)]);
+
+        let output = xl(f, &[p, q, r], &XlOptions::new(2)).unwrap();
+        assert!(!output.is_inconsistent());
+        let x2 = add(f, x[2], 1);
+        let output = xl(f, &[p, q, r, x2], &XlOptions::new(2)).unwrap();
+        assert!(!output.is_inconsistent());
+        let output = xl(f, &[p, q, r, x[2]], &XlOptions::new(2)).unwrap();
+        assert!(output.is_inconsistent());
+
+        options.memory_budget = Some(8);
+        match xl(f, &[p, q, r], &options) {
+            Err(XlError::MemoryBudget { degree, .. }) => assert_eq!(degree, 3),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn xl_budget() {
+        let f = &mut Forest::new();
+        let mut lfsr = 9;
+        let solution: HashSet<Variable> = [1, 4].iter().cloned().collect();
+        let polys = random_system(f, &mut lfsr, 14, &solution);
+
+        // The full expansion has hundreds of rows; the budget has to stop
+        // it after the first few.
+        let mut options = XlOptions::new(5);
+        options.memory_budget = Some(256);
+        match xl(f, &polys, &options) {
+            Err(XlError::MemoryBudget { degree, rows, bytes, .. }) => {
+                assert_eq!(degree, 5);
+                assert!(bytes > 256);
+                assert!(rows < 50);
+            }
+            result => panic!("{:?}", result),
+        }
+
+        // Multiplying by every variable would be millions of products per
+        // equation, so they must not be built ahead of the budget check.
+        options.variables = Some((0..256).map(|var| var as Variable).collect());
+        match xl(f, &polys, &options) {
+            Err(XlError::MemoryBudget { rows, .. }) => assert!(rows < 50),
+            result => panic!("{:?}", result),
+        }
+    }
+}